@@ -0,0 +1,89 @@
+//! Compares per-element decoding through `ElementScalar::read_element` with 
+//! the bulk path in `IdxReader::read_elements`.
+//!
+//! Uses `data/train-images-idx3-ubyte` when present, otherwise an in-memory 
+//! file of the same 60000x28x28 shape. Run with `cargo bench`.
+
+extern crate byteorder;
+extern crate neural_net;
+
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use byteorder::BigEndian;
+use neural_net::mnist::idx::{ElementScalar, IdxHeader, IdxReader};
+
+const ITERATIONS: usize = 5;
+
+fn synthetic<T: ElementScalar>(dims: Vec<u32>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let header = IdxHeader::new(T::element_type(), dims);
+    header.write(&mut bytes).unwrap();
+
+    let payload = header.dimension_sizes.iter().product::<u32>() as usize 
+        * T::element_type().size_in_bytes() as usize;
+    bytes.extend((0..payload).map(|i| (i.wrapping_mul(2_654_435_761) >> 7) as u8));
+    bytes
+}
+
+fn mnist_training_images() -> Vec<u8> {
+    let path = Path::new("data/train-images-idx3-ubyte");
+    match IdxReader::from_file(path) {
+        Ok(mut reader) => {
+            let mut bytes = Vec::new();
+            reader.header().write(&mut bytes).unwrap();
+            bytes.extend(reader.read_bytes_to_end().unwrap());
+            bytes
+        },
+        Err(_) => synthetic::<u8>(vec![60000, 28, 28]),
+    }
+}
+
+fn best_of<F: FnMut()>(mut f: F) -> Duration {
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn per_element<T: ElementScalar>(bytes: &[u8], out: &mut [T]) {
+    let mut reader = io::Cursor::new(bytes);
+    IdxReader::read_header(&mut reader).unwrap();
+    for x in out.iter_mut() {
+        *x = T::read_element::<BigEndian, _>(&mut reader).unwrap();
+    }
+}
+
+fn bulk<T: ElementScalar>(bytes: &[u8], out: &mut [T]) {
+    let mut reader = IdxReader::new(io::Cursor::new(bytes)).unwrap();
+    reader.read_elements(out).unwrap();
+}
+
+fn bench<T: ElementScalar>(name: &str, bytes: &[u8]) {
+    let reader = IdxReader::new(io::Cursor::new(bytes)).unwrap();
+    let mut out = vec![T::default(); reader.num_elems()];
+    let mb = (bytes.len() as f64) / (1024.0 * 1024.0);
+    let rate = |d: Duration| mb / d.as_secs_f64();
+
+    let slow = best_of(|| per_element(bytes, &mut out));
+    let fast = best_of(|| bulk(bytes, &mut out));
+
+    println!("{:<26} {:>8.1} MB  per-element {:>8.1} MB/s  bulk {:>8.1} MB/s  {:>5.1}x",
+             name, mb, rate(slow), rate(fast), slow.as_secs_f64() / fast.as_secs_f64());
+}
+
+fn main() {
+    bench::<u8>("train-images (u8)", &mnist_training_images());
+
+    let dims = vec![10000, 28, 28];
+    bench::<i8>("synthetic i8", &synthetic::<i8>(dims.clone()));
+    bench::<i16>("synthetic i16", &synthetic::<i16>(dims.clone()));
+    bench::<i32>("synthetic i32", &synthetic::<i32>(dims.clone()));
+    bench::<f32>("synthetic f32", &synthetic::<f32>(dims.clone()));
+    bench::<f64>("synthetic f64", &synthetic::<f64>(dims));
+}
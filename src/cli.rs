@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rand;
+use rand::{SeedableRng, StdRng};
+
+use neural_net::mnist::Dataset;
+use neural_net::mnist::augment::{Augmentation, Augmenter};
+use neural_net::mnist::dataset::{normalize, NUM_CLASSES};
+use neural_net::mnist::error::MnistError;
+use neural_net::mnist::idx::IdxReader;
+use neural_net::mnist::random_access::{RandomAccess, SeekReader};
+use neural_net::mnist::source::FileSource;
+use neural_net::mnist::split::{self, Subset};
+use neural_net::net::activation::{self, Activation, Sigmoid};
+use neural_net::net::cost::{self, Canonical};
+use neural_net::net::dropout::Dropout;
+use neural_net::net::error::NetError;
+use neural_net::net::eval::Evaluation;
+use neural_net::net::geom::Geometry;
+use neural_net::net::init::Initializer;
+use neural_net::net::optimizer;
+use neural_net::net::regularization::Regularization;
+use neural_net::net::schedule::{EarlyStopping, Schedule};
+use neural_net::net::serialize::Checkpoint;
+use neural_net::net::{Network, Sgd};
+
+pub const USAGE: &str = "\
+Usage: neural_net <command> [options]
+
+Commands:
+  train    --geometry 784,30,10 --output FILE [--epochs 30] [--batch-size 10]
+           [--learning-rate 3.0] [--cost quadratic|cross_entropy|log_likelihood]
+           [--output-activation sigmoid|softmax|...]
+           [--l1 LAMBDA] [--l2 LAMBDA] [--dropout RATE]
+           [--optimizer sgd|momentum|nesterov|adagrad|rmsprop|adam|adamw]
+           [--init normal|scaled-normal|xavier-uniform|xavier-normal|he-normal|
+                   he-uniform|orthogonal[:GAIN]|constant:VALUE[,...]] [--seed N]
+           [--resume FILE] [--validation 0] [--split last|random|stratified]
+           [--patience N]
+           [--schedule constant|step:EVERY:FACTOR|exp:GAMMA|
+                       cosine:PERIOD[:MULT[:MIN]]|plateau:PATIENCE[:FACTOR]]
+           [--train-images FILE] [--train-labels FILE]
+           [--test-images FILE] [--test-labels FILE]
+  eval     --model FILE [--images FILE] [--labels FILE] [--top-k 3]
+  predict  --model FILE --images FILE [--index 0]
+  augment  --pipeline translate:MAX|rotate:DEGREES|scale:MIN:MAX|shear:MAX|
+                      elastic:ALPHA:SIGMA|noise:STD_DEV|erase:P[:MIN:MAX][,...]
+           --output-images FILE --output-labels FILE [--copies 1] [--seed N]
+           [--images FILE] [--labels FILE]
+  inspect  FILE...
+  help";
+
+const TRAIN_IMAGES: &str = "data/train-images-idx3-ubyte";
+const TRAIN_LABELS: &str = "data/train-labels-idx1-ubyte";
+const TEST_IMAGES: &str = "data/t10k-images-idx3-ubyte";
+const TEST_LABELS: &str = "data/t10k-labels-idx1-ubyte";
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Mnist(MnistError),
+    Net(NetError),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            CliError::Usage(_) => 2,
+            CliError::Mnist(_) | CliError::Net(_) => 1,
+        }
+    }
+}
+
+impl From<MnistError> for CliError {
+    fn from(error: MnistError) -> CliError {
+        CliError::Mnist(error)
+    }
+}
+
+impl From<NetError> for CliError {
+    fn from(error: NetError) -> CliError {
+        CliError::Net(error)
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::Usage(ref msg) => write!(f, "{}", msg),
+            CliError::Mnist(ref err) => write!(f, "{}", err),
+            CliError::Net(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+type Result<T> = ::std::result::Result<T, CliError>;
+
+/// `--key value` options and positional arguments following a subcommand.
+struct Args {
+    options: HashMap<String, String>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Args> {
+        let mut options = HashMap::new();
+        let mut positional = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                let value = iter.next()
+                    .ok_or_else(|| CliError::Usage(format!("missing value for --{}", key)))?;
+                options.insert(key.to_string(), value.clone());
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Args { options, positional })
+    }
+
+    fn path(&self, key: &str, default: Option<&str>) -> Result<PathBuf> {
+        self.options.get(key).map(|s| s.as_str()).or(default)
+            .map(PathBuf::from)
+            .ok_or_else(|| CliError::Usage(format!("--{} is required", key)))
+    }
+
+    fn value<T: FromStr>(&self, key: &str, default: T) -> Result<T> {
+        match self.options.get(key) {
+            Some(s) => s.parse()
+                .map_err(|_| CliError::Usage(format!("invalid value '{}' for --{}", s, key))),
+            None => Ok(default),
+        }
+    }
+
+    fn check_known(&self, known: &[&str]) -> Result<()> {
+        match self.options.keys().find(|k| !known.contains(&k.as_str())) {
+            Some(key) => Err(CliError::Usage(format!("unknown option --{}", key))),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let (command, rest) = args.split_first()
+        .ok_or_else(|| CliError::Usage("no command given".to_string()))?;
+    let args = Args::parse(rest)?;
+
+    match command.as_str() {
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        "train" => train(&args),
+        "eval" => eval(&args),
+        "predict" => predict(&args),
+        "augment" => augment(&args),
+        "inspect" => inspect(&args),
+        _ => Err(CliError::Usage(format!("unknown command '{}'", command))),
+    }
+}
+
+fn train(args: &Args) -> Result<()> {
+    args.check_known(&["geometry", "epochs", "batch-size", "learning-rate", "cost", 
+                       "output-activation", "l1", "l2", "dropout", "optimizer", "resume", "output",
+                       "schedule", "validation", "split", "patience", "init", "seed",
+                       "train-images", "train-labels", "test-images", "test-labels"])?;
+
+    let seed = match args.options.get("seed") {
+        Some(_) => args.value("seed", 0)?,
+        None => rand::random(),
+    };
+    let mut rng = StdRng::from_seed(&[seed]);
+
+    // A resumed run continues with the saved network and, unless another is 
+    // asked for, the saved optimizer state.
+    let (mut net, saved_optimizer) = match args.options.get("resume") {
+        Some(path) => {
+            if let Some(key) = ["geometry", "init", "output-activation"].iter()
+                .find(|&&key| args.options.contains_key(key)) 
+            {
+                return Err(CliError::Usage(format!("--{} can't be used with --resume", key)));
+            }
+            let checkpoint = Checkpoint::load(Path::new(path))?;
+            (checkpoint.network, checkpoint.optimizer)
+        },
+        None => {
+            let geometry_arg = args.options.get("geometry")
+                .ok_or_else(|| CliError::Usage("--geometry is required".to_string()))?;
+            let geometry: Geometry = geometry_arg.parse()
+                .map_err(|e| CliError::Usage(format!("invalid --geometry: {}", e)))?;
+            let initializers = initializers(args, geometry.num_layers() - 1)?;
+            let output_name = args.value("output-activation", "sigmoid".to_string())?;
+            let output_activation = activation::from_name(&output_name, &[])
+                .ok_or_else(|| CliError::Usage(format!("unknown activation '{}'", output_name)))?;
+            let mut activations: Vec<Box<dyn Activation>> = (1..initializers.len())
+                .map(|_| Box::new(Sigmoid) as Box<dyn Activation>)
+                .collect();
+            activations.push(output_activation);
+            (Network::with_initializers(geometry, activations, &initializers, &mut rng), None)
+        },
+    };
+    let optimizer = match (args.options.get("optimizer"), saved_optimizer) {
+        (Some(name), _) => optimizer::from_name(name)
+            .ok_or_else(|| CliError::Usage(format!("unknown optimizer '{}'", name)))?,
+        (None, Some(saved)) => saved,
+        (None, None) => optimizer::from_name("sgd").unwrap(),
+    };
+    let output = args.path("output", None)?;
+    let epochs = args.value("epochs", 30)?;
+    let batch_size = args.value("batch-size", 10)?;
+    if batch_size == 0 {
+        return Err(CliError::Usage("--batch-size must be positive".to_string()));
+    }
+    let learning_rate = args.value("learning-rate", 3.0)?;
+    let cost_name = args.value("cost", "quadratic".to_string())?;
+    let cost = cost::from_name(&cost_name)
+        .ok_or_else(|| CliError::Usage(format!("unknown cost '{}'", cost_name)))?;
+    // Log-likelihood only looks at the target's output, so without softmax 
+    // tying the outputs together nothing pushes the others down.
+    let output_activation = net.activation(net.num_layers() - 2);
+    if cost.canonical() == Some(Canonical::LogLikelihood) 
+        && output_activation.canonical() != cost.canonical() 
+    {
+        return Err(CliError::Usage(format!(
+            "--cost log_likelihood needs a softmax output layer, not {}", 
+            output_activation.name())));
+    }
+    let regularization = match (args.value("l1", 0.0)?, args.value("l2", 0.0)?) {
+        (l1, l2) if l1 != 0.0 && l2 != 0.0 => Regularization::ElasticNet { l1, l2 },
+        (l1, _) if l1 != 0.0 => Regularization::L1(l1),
+        (_, l2) if l2 != 0.0 => Regularization::L2(l2),
+        _ => Regularization::None,
+    };
+
+    let dropout: f64 = args.value("dropout", 0.0)?;
+    if !(0.0..1.0).contains(&dropout) {
+        return Err(CliError::Usage("--dropout must be in [0, 1)".to_string()));
+    }
+
+    let training_set = Dataset::from_files(&args.path("train-images", Some(TRAIN_IMAGES))?,
+                                           &args.path("train-labels", Some(TRAIN_LABELS))?)?;
+    // Both fresh and resumed networks have to fit the images and the digit 
+    // classes, or training would fail deep inside the matrix code.
+    check_input_size(&net, training_set.images().first().map_or(0, |i| i.data().len()))?;
+    check_output_size(&net)?;
+    let test_data = if args.options.contains_key("test-images") {
+        Some(Dataset::from_files(&args.path("test-images", None)?, 
+                                 &args.path("test-labels", Some(TEST_LABELS))?)?)
+    } else {
+        None
+    };
+
+    let schedule: Schedule = args.value("schedule", Schedule::Constant)?;
+    let patience: usize = args.value("patience", 0)?;
+    let validation_size: usize = args.value("validation", 0)?;
+    if validation_size >= training_set.len() {
+        return Err(CliError::Usage("--validation must be smaller than the training set".to_string()));
+    }
+    // Both are driven by validation accuracy and would silently do nothing 
+    // without it.
+    if validation_size == 0 {
+        if patience > 0 {
+            return Err(CliError::Usage("--patience needs --validation".to_string()));
+        }
+        if let Schedule::Plateau { .. } = schedule {
+            return Err(CliError::Usage("--schedule plateau needs --validation".to_string()));
+        }
+    }
+    // Nielsen's setup holds out the last items; the other splits draw from 
+    // the whole set.
+    let sizes = [training_set.len() - validation_size, validation_size];
+    let parts = match args.value("split", "last".to_string())?.as_str() {
+        "last" => vec![(0..sizes[0]).collect(), (sizes[0]..training_set.len()).collect()],
+        "random" => split::random_split(training_set.len(), &sizes, &mut rng)?,
+        "stratified" => split::stratified_split(training_set.labels(), &sizes, &mut rng)?,
+        other => return Err(CliError::Usage(format!("unknown split '{}'", other))),
+    };
+    let training_data = Subset::new(&training_set, &parts[0])?;
+    let validation_data = Subset::new(&training_set, &parts[1])?;
+
+    let mut sgd = Sgd::with_cost(epochs, batch_size, learning_rate, cost);
+    sgd.regularization = regularization;
+    sgd.optimizer = optimizer;
+    sgd.schedule = schedule;
+    if patience > 0 {
+        sgd.early_stopping = Some(EarlyStopping::new(patience));
+    }
+    sgd.dropout = Dropout::uniform(net.num_layers() - 2, dropout);
+    println!("Training {} on {} items with {}", 
+             net.geometry(), training_data.len(), sgd.optimizer.name());
+
+    let history = sgd.fit(&mut net, &training_data, &validation_data, &mut rng, 
+                          |stats, net| {
+        print!("Epoch {} (rate {:.4}), cost {:.4}", 
+               stats.epoch, stats.learning_rate, stats.training_cost);
+        if let (Some(cost), Some(accuracy)) = (stats.validation_cost, stats.validation_accuracy) {
+            print!(", validation cost {:.4} {:.2}%", cost, 100.0 * accuracy);
+        }
+        match test_data {
+            Some(ref test) => println!(": {} / {}", net.evaluate(test), test.len()),
+            None => println!(),
+        }
+    });
+    if let Some(best) = history.best_epoch {
+        println!("{} at epoch {}, keeping epoch {}", 
+                 if history.stopped_early { "Stopped early" } else { "Finished" },
+                 history.epochs.len() - 1, best);
+    }
+
+    Checkpoint::new(net, Some(sgd.optimizer)).save(&output)?;
+    println!("Saved model to {}", output.display());
+    Ok(())
+}
+
+/// Parses `--init` as either one initializer for every layer or a comma 
+/// separated list with one per layer.
+fn initializers(args: &Args, num_layers: usize) -> Result<Vec<Initializer>> {
+    let arg = match args.options.get("init") {
+        Some(arg) => arg,
+        None => return Ok(vec![Initializer::StandardNormal; num_layers]),
+    };
+    let inits = arg.split(',')
+        .map(|s| s.trim().parse::<Initializer>()
+             .map_err(|e| CliError::Usage(format!("invalid --init: {}", e))))
+        .collect::<Result<Vec<_>>>()?;
+    match inits.len() {
+        1 => Ok(vec![inits[0]; num_layers]),
+        n if n == num_layers => Ok(inits),
+        n => Err(CliError::Usage(format!("--init lists {} initializers for {} layers", 
+                                         n, num_layers))),
+    }
+}
+
+/// Writes a copy of a data set followed by `--copies` augmented versions of 
+/// every image.
+fn augment(args: &Args) -> Result<()> {
+    args.check_known(&["pipeline", "copies", "seed", "images", "labels", 
+                       "output-images", "output-labels"])?;
+
+    let pipeline = args.options.get("pipeline")
+        .ok_or_else(|| CliError::Usage("--pipeline is required".to_string()))?
+        .split(',')
+        .map(|s| s.trim().parse::<Augmentation>()
+             .map_err(|e| CliError::Usage(format!("invalid --pipeline: {}", e))))
+        .collect::<Result<Vec<_>>>()?;
+    let copies = args.value("copies", 1)?;
+    let seed = match args.options.get("seed") {
+        Some(_) => args.value("seed", 0)?,
+        None => rand::random(),
+    };
+    let output_images = args.path("output-images", None)?;
+    let output_labels = args.path("output-labels", None)?;
+
+    let data = Dataset::from_files(&args.path("images", Some(TRAIN_IMAGES))?, 
+                                   &args.path("labels", Some(TRAIN_LABELS))?)?;
+    let expanded = Augmenter::seeded(pipeline, seed).expand_dataset(&data, copies);
+    expanded.save(&output_images, &output_labels)?;
+    println!("Wrote {} items to {} and {}", 
+             expanded.len(), output_images.display(), output_labels.display());
+    Ok(())
+}
+
+fn eval(args: &Args) -> Result<()> {
+    args.check_known(&["model", "images", "labels", "top-k"])?;
+
+    let net = Network::load(&args.path("model", None)?)?;
+    let data = Dataset::from_files(&args.path("images", Some(TEST_IMAGES))?, 
+                                   &args.path("labels", Some(TEST_LABELS))?)?;
+    let top_k = args.value("top-k", 3)?;
+    if top_k == 0 {
+        return Err(CliError::Usage("--top-k must be positive".to_string()));
+    }
+    check_input_size(&net, data.images().first().map_or(0, |i| i.data().len()))?;
+    check_output_size(&net)?;
+
+    println!("{}", Evaluation::from_dataset(&net, &data, top_k)?);
+    Ok(())
+}
+
+fn predict(args: &Args) -> Result<()> {
+    args.check_known(&["model", "images", "index"])?;
+
+    let net = Network::load(&args.path("model", None)?)?;
+    let index: usize = args.value("index", 0)?;
+    let path = args.path("images", None)?;
+    // Uncompressed files are read straight from the item's offset; a gzip 
+    // stream has to be decoded up to it.
+    let source = FileSource::open(&path).map_err(MnistError::from)?;
+    let image = if source.is_compressed() {
+        let reader = IdxReader::new(source)?;
+        let len = reader.header().num_items();
+        reader.items::<u8>()?.nth(index)
+            .ok_or(MnistError::IndexOutOfRange { index, len })??
+    } else {
+        SeekReader::from_file(&path)?.read_item_at::<u8>(index)?
+    };
+    let input = normalize(&image);
+    check_input_size(&net, input.len())?;
+
+    let output = net.feed_forward(&input);
+    println!("Item {}: predicted {}", index, net.classify(&input));
+    for (class, activation) in output.iter().enumerate() {
+        println!("  {}: {:.4}", class, activation);
+    }
+    Ok(())
+}
+
+fn inspect(args: &Args) -> Result<()> {
+    args.check_known(&[])?;
+    if args.positional.is_empty() {
+        return Err(CliError::Usage("inspect needs at least one file".to_string()));
+    }
+
+    for file in &args.positional {
+        let mut source = FileSource::open(Path::new(file)).map_err(MnistError::from)?;
+        let header = IdxReader::read_header(&mut source)?;
+        println!("{}", file);
+        println!("  compressed:   {}", source.is_compressed());
+        println!("  element type: {} (0x{:02x})", header.elem_type, header.elem_type as u8);
+        println!("  header:       {}", header);
+        println!("  items:        {}", header.num_items());
+        println!("  item size:    {} elements", header.item_size());
+
+        // A file whose length doesn't match still gets its header shown 
+        // before the problem is reported.
+        if !source.is_compressed() {
+            let len = fs::metadata(file).map_err(MnistError::from)?.len();
+            header.check_payload_len(len.saturating_sub(header.encoded_len()))?;
+        }
+    }
+    Ok(())
+}
+
+fn check_input_size(net: &Network, input_size: usize) -> Result<()> {
+    if input_size != net.geometry().input_size() {
+        return Err(CliError::Usage(format!(
+            "model expects {} inputs but the images have {} pixels", 
+            net.geometry().input_size(), input_size)));
+    }
+    Ok(())
+}
+
+fn check_output_size(net: &Network) -> Result<()> {
+    let outputs = net.geometry().output_size();
+    if outputs != NUM_CLASSES {
+        return Err(CliError::Usage(format!(
+            "model has {} outputs but there are {} digit classes", outputs, NUM_CLASSES)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> Result<()> {
+        run(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    fn usage_error(result: Result<()>) -> String {
+        match result {
+            Err(CliError::Usage(msg)) => msg,
+            other => panic!("expected a usage error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn train_needs_softmax_for_log_likelihood() {
+        let train = ["train", "--geometry", "4,3,10", "--output", "/nonexistent/model", 
+                     "--train-images", "/nonexistent/images", "--cost", "log_likelihood"];
+        let msg = usage_error(run_args(&train));
+        assert!(msg.contains("softmax"), "{}", msg);
+
+        // With a softmax output the arguments are fine and it gets as far as 
+        // loading the images.
+        let mut softmax = train.to_vec();
+        softmax.extend(&["--output-activation", "softmax"]);
+        match run_args(&softmax) {
+            Err(CliError::Mnist(_)) => (),
+            other => panic!("expected the missing images to fail, got {:?}", other),
+        }
+
+        let mut unknown = train.to_vec();
+        unknown.extend(&["--output-activation", "swish"]);
+        assert!(usage_error(run_args(&unknown)).contains("swish"));
+    }
+
+    #[test]
+    fn resume_rejects_network_options() {
+        for &key in &["--geometry", "--init", "--output-activation"] {
+            let value = if key == "--geometry" { "4,3,10" } else { "xavier-normal" };
+            let msg = usage_error(run_args(&["train", "--resume", "/nonexistent/model", 
+                                             "--output", "/nonexistent/out", key, value]));
+            assert!(msg.contains(key), "{}", msg);
+        }
+    }
+}
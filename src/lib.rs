@@ -0,0 +1,8 @@
+extern crate rand;
+extern crate byteorder;
+extern crate memmap2;
+extern crate flate2;
+
+pub mod mnist;
+pub mod net;
+pub mod math;
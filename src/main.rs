@@ -1,17 +1,19 @@
 extern crate rand;
-extern crate byteorder;
+extern crate neural_net;
 
-pub mod mnist;
-pub mod net;
-pub mod math;
+mod cli;
 
-use std::path::Path;
-use mnist::idx;
+use std::env;
+use std::process;
 
 fn main() {
-    let labels = 
-        idx::IdxReader::from_file(&Path::new("data/train-labels-idx1-ubyte")).unwrap();
-    let images = 
-        idx::IdxReader::from_file(&Path::new("data/train-images-idx3-ubyte")).unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
 
+    if let Err(e) = cli::run(&args) {
+        eprintln!("error: {}", e);
+        if let cli::CliError::Usage(_) = e {
+            eprintln!("\n{}", cli::USAGE);
+        }
+        process::exit(e.exit_code());
+    }
 }
@@ -0,0 +1,214 @@
+use std::ops::{Index, IndexMut};
+
+use super::scalar::Scalar;
+use super::vector::Vector;
+
+/// A dense row-major matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix<T: Scalar> {
+    rows: usize,
+    cols: usize,
+    elems: Vec<T>,
+}
+
+impl<T: Scalar> Matrix<T> {
+    pub fn new(rows: usize, cols: usize, elems: Vec<T>) -> Matrix<T> {
+        assert_eq!(rows*cols, elems.len());
+        Matrix {
+            rows,
+            cols,
+            elems,
+        }
+    }
+    pub fn zeros(rows: usize, cols: usize) -> Matrix<T> {
+        Matrix::new(rows, cols, vec![T::zero(); rows*cols])
+    }
+    pub fn identity(size: usize) -> Matrix<T> {
+        Matrix::from_fn(size, size, |r, c| if r == c { T::one() } else { T::zero() })
+    }
+    pub fn from_fn<F: FnMut(usize, usize) -> T>(rows: usize, cols: usize, mut f: F) -> Matrix<T> {
+        let mut elems = Vec::with_capacity(rows*cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                elems.push(f(r, c));
+            }
+        }
+        Matrix::new(rows, cols, elems)
+    }
+    /// The outer product `column * row^T`.
+    pub fn outer(column: &Vector<T>, row: &Vector<T>) -> Matrix<T> {
+        Matrix::from_fn(column.len(), row.len(), |r, c| column[r] * row[c])
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+    pub fn as_slice(&self) -> &[T] {
+        &self.elems
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.elems
+    }
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.elems[row*self.cols..(row+1)*self.cols]
+    }
+    pub fn row_mut(&mut self, row: usize) -> &mut [T] {
+        &mut self.elems[row*self.cols..(row+1)*self.cols]
+    }
+
+    pub fn mul_vec(&self, v: &Vector<T>) -> Vector<T> {
+        assert_eq!(self.cols, v.len());
+        Vector::from_fn(self.rows, |r| {
+            self.row(r).iter().zip(v.iter()).fold(T::zero(), |sum, (&a, &b)| sum + a*b)
+        })
+    }
+    /// Computes `self^T * v` without materializing the transpose.
+    pub fn transpose_mul_vec(&self, v: &Vector<T>) -> Vector<T> {
+        assert_eq!(self.rows, v.len());
+        let mut out = Vector::zeros(self.cols);
+        for r in 0..self.rows {
+            let scale = v[r];
+            for (o, &a) in out.as_mut_slice().iter_mut().zip(self.row(r).iter()) {
+                *o += scale * a;
+            }
+        }
+        out
+    }
+    pub fn mul(&self, other: &Matrix<T>) -> Matrix<T> {
+        assert_eq!(self.cols, other.rows);
+        let mut out = Matrix::zeros(self.rows, other.cols);
+        for r in 0..self.rows {
+            for k in 0..self.cols {
+                let a = self[(r, k)];
+                for (o, &b) in out.row_mut(r).iter_mut().zip(other.row(k).iter()) {
+                    *o += a * b;
+                }
+            }
+        }
+        out
+    }
+    pub fn transpose(&self) -> Matrix<T> {
+        Matrix::from_fn(self.cols, self.rows, |r, c| self[(c, r)])
+    }
+
+    pub fn hadamard(&self, other: &Matrix<T>) -> Matrix<T> {
+        self.assert_same_shape(other);
+        Matrix::new(self.rows, self.cols, 
+            self.elems.iter().zip(other.elems.iter()).map(|(&a, &b)| a*b).collect())
+    }
+    pub fn map<F: FnMut(T) -> T>(&self, mut f: F) -> Matrix<T> {
+        Matrix::new(self.rows, self.cols, self.elems.iter().map(|&x| f(x)).collect())
+    }
+    pub fn map_in_place<F: FnMut(T) -> T>(&mut self, mut f: F) {
+        for x in self.elems.iter_mut() {
+            *x = f(*x);
+        }
+    }
+
+    pub fn add_assign(&mut self, other: &Matrix<T>) {
+        self.add_scaled(T::one(), other);
+    }
+    pub fn sub_assign(&mut self, other: &Matrix<T>) {
+        self.add_scaled(-T::one(), other);
+    }
+    /// `self += alpha * other`
+    pub fn add_scaled(&mut self, alpha: T, other: &Matrix<T>) {
+        self.assert_same_shape(other);
+        for (a, &b) in self.elems.iter_mut().zip(other.elems.iter()) {
+            *a += alpha * b;
+        }
+    }
+    /// `self += alpha * column * row^T`, accumulating an outer product in place.
+    pub fn add_outer(&mut self, alpha: T, column: &Vector<T>, row: &Vector<T>) {
+        assert_eq!(self.rows, column.len());
+        assert_eq!(self.cols, row.len());
+        for r in 0..self.rows {
+            let scale = alpha * column[r];
+            for (a, &b) in self.row_mut(r).iter_mut().zip(row.iter()) {
+                *a += scale * b;
+            }
+        }
+    }
+    pub fn scale(&mut self, alpha: T) {
+        for x in self.elems.iter_mut() {
+            *x *= alpha;
+        }
+    }
+
+    fn assert_same_shape(&self, other: &Matrix<T>) {
+        assert_eq!((self.rows, self.cols), (other.rows, other.cols));
+    }
+}
+
+impl<T: Scalar> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        &self.elems[row*self.cols + col]
+    }
+}
+
+impl<T: Scalar> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        &mut self.elems[row*self.cols + col]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn a() -> Matrix<f64> {
+        Matrix::new(2, 3, vec![1.0, 2.0, 3.0, 
+                               4.0, 5.0, 6.0])
+    }
+
+    #[test]
+    fn mul_vec() {
+        let v = Vector::new(vec![1.0, 0.0, -1.0]);
+        assert_eq!(a().mul_vec(&v).as_slice(), &[-2.0, -2.0]);
+
+        let u = Vector::new(vec![1.0, 2.0]);
+        assert_eq!(a().transpose_mul_vec(&u).as_slice(), &[9.0, 12.0, 15.0]);
+    }
+
+    #[test]
+    fn mul_and_transpose() {
+        let t = a().transpose();
+        assert_eq!((t.rows(), t.cols()), (3, 2));
+        assert_eq!(t.as_slice(), &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+
+        let p = a().mul(&t);
+        assert_eq!(p, Matrix::new(2, 2, vec![14.0, 32.0, 32.0, 77.0]));
+        assert_eq!(a().mul(&Matrix::identity(3)), a());
+    }
+
+    #[test]
+    fn elementwise() {
+        let h = a().hadamard(&a());
+        assert_eq!(h.as_slice(), &[1.0, 4.0, 9.0, 16.0, 25.0, 36.0]);
+        assert_eq!(a().map(|x| x - 1.0).as_slice(), &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let mut m = a();
+        m.add_assign(&a());
+        m.scale(0.5);
+        assert_eq!(m, a());
+        m.sub_assign(&a());
+        assert_eq!(m, Matrix::zeros(2, 3));
+    }
+
+    #[test]
+    fn outer_product() {
+        let col = Vector::new(vec![1.0f32, 2.0]);
+        let row = Vector::new(vec![3.0, 4.0, 5.0]);
+        let o = Matrix::outer(&col, &row);
+        assert_eq!(o.as_slice(), &[3.0, 4.0, 5.0, 6.0, 8.0, 10.0]);
+
+        let mut acc = Matrix::zeros(2, 3);
+        acc.add_outer(2.0, &col, &row);
+        assert_eq!(acc, o.map(|x| 2.0 * x));
+    }
+}
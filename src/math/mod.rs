@@ -0,0 +1,7 @@
+pub mod scalar;
+pub mod vector;
+pub mod matrix;
+
+pub use self::scalar::Scalar;
+pub use self::vector::Vector;
+pub use self::matrix::Matrix;
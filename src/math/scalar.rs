@@ -0,0 +1,20 @@
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Div, Neg, AddAssign, SubAssign, MulAssign};
+
+pub trait Scalar: Copy + Default + PartialOrd + fmt::Debug + fmt::Display
+    + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self> + Div<Output=Self> 
+    + Neg<Output=Self> + AddAssign + SubAssign + MulAssign
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+}
+
+impl Scalar for f32 {
+    fn zero() -> f32 { 0.0 }
+    fn one() -> f32 { 1.0 }
+}
+
+impl Scalar for f64 {
+    fn zero() -> f64 { 0.0 }
+    fn one() -> f64 { 1.0 }
+}
@@ -0,0 +1,137 @@
+use std::ops::{Index, IndexMut};
+
+use super::scalar::Scalar;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vector<T: Scalar> {
+    elems: Vec<T>,
+}
+
+impl<T: Scalar> Vector<T> {
+    pub fn new(elems: Vec<T>) -> Vector<T> {
+        Vector {
+            elems,
+        }
+    }
+    pub fn zeros(len: usize) -> Vector<T> {
+        Vector::new(vec![T::zero(); len])
+    }
+    pub fn from_slice(elems: &[T]) -> Vector<T> {
+        Vector::new(elems.to_vec())
+    }
+    pub fn from_fn<F: FnMut(usize) -> T>(len: usize, f: F) -> Vector<T> {
+        Vector::new((0..len).map(f).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.elems.is_empty()
+    }
+    pub fn as_slice(&self) -> &[T] {
+        &self.elems
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.elems
+    }
+    pub fn into_vec(self) -> Vec<T> {
+        self.elems
+    }
+    pub fn iter(&self) -> ::std::slice::Iter<'_, T> {
+        self.elems.iter()
+    }
+
+    pub fn dot(&self, other: &Vector<T>) -> T {
+        assert_eq!(self.len(), other.len());
+        self.elems.iter().zip(other.elems.iter())
+            .fold(T::zero(), |sum, (&a, &b)| sum + a*b)
+    }
+
+    pub fn hadamard(&self, other: &Vector<T>) -> Vector<T> {
+        assert_eq!(self.len(), other.len());
+        Vector::new(self.elems.iter().zip(other.elems.iter()).map(|(&a, &b)| a*b).collect())
+    }
+
+    pub fn map<F: FnMut(T) -> T>(&self, mut f: F) -> Vector<T> {
+        Vector::new(self.elems.iter().map(|&x| f(x)).collect())
+    }
+    pub fn map_in_place<F: FnMut(T) -> T>(&mut self, mut f: F) {
+        for x in self.elems.iter_mut() {
+            *x = f(*x);
+        }
+    }
+
+    pub fn add_assign(&mut self, other: &Vector<T>) {
+        self.add_scaled(T::one(), other);
+    }
+    pub fn sub_assign(&mut self, other: &Vector<T>) {
+        self.add_scaled(-T::one(), other);
+    }
+    /// `self += alpha * other`
+    pub fn add_scaled(&mut self, alpha: T, other: &Vector<T>) {
+        assert_eq!(self.len(), other.len());
+        for (a, &b) in self.elems.iter_mut().zip(other.elems.iter()) {
+            *a += alpha * b;
+        }
+    }
+    pub fn scale(&mut self, alpha: T) {
+        for x in self.elems.iter_mut() {
+            *x *= alpha;
+        }
+    }
+
+    pub fn sum(&self) -> T {
+        self.elems.iter().fold(T::zero(), |sum, &x| sum + x)
+    }
+    pub fn argmax(&self) -> usize {
+        let mut best = 0;
+        for (i, &x) in self.elems.iter().enumerate() {
+            if x > self.elems[best] {
+                best = i;
+            }
+        }
+        best
+    }
+}
+
+impl<T: Scalar> Index<usize> for Vector<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        &self.elems[i]
+    }
+}
+
+impl<T: Scalar> IndexMut<usize> for Vector<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        &mut self.elems[i]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dot_and_hadamard() {
+        let a = Vector::new(vec![1.0, 2.0, 3.0]);
+        let b = Vector::new(vec![4.0, -5.0, 6.0]);
+
+        assert_eq!(a.dot(&b), 12.0);
+        assert_eq!(a.hadamard(&b), Vector::new(vec![4.0, -10.0, 18.0]));
+    }
+
+    #[test]
+    fn in_place_ops() {
+        let mut a = Vector::new(vec![1.0f32, 2.0, 3.0]);
+        a.add_assign(&Vector::new(vec![1.0, 1.0, 1.0]));
+        assert_eq!(a.as_slice(), &[2.0, 3.0, 4.0]);
+        a.scale(0.5);
+        assert_eq!(a.as_slice(), &[1.0, 1.5, 2.0]);
+        a.add_scaled(-2.0, &Vector::new(vec![1.0, 0.0, 1.0]));
+        assert_eq!(a.as_slice(), &[-1.0, 1.5, 0.0]);
+        assert_eq!(a.map(|x| x * x).as_slice(), &[1.0, 2.25, 0.0]);
+        assert_eq!(a.argmax(), 1);
+    }
+}
@@ -0,0 +1,486 @@
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{Rng, SeedableRng, StdRng};
+use rand::distributions::{Normal, IndependentSample};
+
+use super::batch::Examples;
+use super::dataset::{Dataset, NUM_CLASSES};
+use super::idx::{ElementScalar, Item};
+
+/// Element types augmentation can work on. Pixels are handled as intensities
+/// where `0` is the background; for `u8` these are scaled to `[0, 1]`.
+pub trait Pixel: ElementScalar {
+    fn to_intensity(self) -> f64;
+    fn from_intensity(x: f64) -> Self;
+}
+
+impl Pixel for u8 {
+    fn to_intensity(self) -> f64 {
+        self as f64 / 255.0
+    }
+    fn from_intensity(x: f64) -> u8 {
+        (x.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+}
+
+impl Pixel for f32 {
+    fn to_intensity(self) -> f64 {
+        self as f64
+    }
+    fn from_intensity(x: f64) -> f32 {
+        x as f32
+    }
+}
+
+/// The widest smoothing `FromStr` accepts for `Elastic`. The blur kernel spans 
+/// six of these, and more than that only flattens the field out.
+pub const MAX_ELASTIC_SIGMA: f64 = 16.0;
+
+/// A random transformation of an image. The geometric ones resample with
+/// bilinear interpolation about the image centre and fill in background
+/// where they uncover the border.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Augmentation {
+    /// Shifts by up to `max` pixels along each axis.
+    Translate { max: f64 },
+    /// Rotates by up to `max_degrees` either way.
+    Rotate { max_degrees: f64 },
+    /// Scales by a factor drawn from `[min, max]`.
+    Scale { min: f64, max: f64 },
+    /// Shears horizontally by a factor of up to `max` either way.
+    Shear { max: f64 },
+    /// Simard et al.'s elastic distortion: a field of uniform random
+    /// displacements, smoothed by a Gaussian of width `sigma` and scaled by
+    /// `alpha` pixels.
+    Elastic { alpha: f64, sigma: f64 },
+    /// Adds `N(0, std_dev)` to every intensity.
+    Noise { std_dev: f64 },
+    /// With probability `probability`, clears a rectangle covering a fraction
+    /// of the image drawn from `[min_area, max_area]`.
+    Erase { probability: f64, min_area: f64, max_area: f64 },
+}
+
+/// One `height x width` slice of an item, as intensities.
+struct Plane {
+    width: usize,
+    height: usize,
+    pixels: Vec<f64>,
+}
+
+impl Plane {
+    /// The intensity at `(x, y)`, interpolated between pixel centres, with
+    /// background outside the image.
+    fn sample(&self, x: f64, y: f64) -> f64 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let at = |xi: f64, yi: f64| {
+            if xi < 0.0 || yi < 0.0 || xi >= self.width as f64 || yi >= self.height as f64 {
+                0.0
+            } else {
+                self.pixels[yi as usize * self.width + xi as usize]
+            }
+        };
+        at(x0, y0) * (1.0 - fx) * (1.0 - fy) + at(x0 + 1.0, y0) * fx * (1.0 - fy)
+            + at(x0, y0 + 1.0) * (1.0 - fx) * fy + at(x0 + 1.0, y0 + 1.0) * fx * fy
+    }
+
+    /// Resamples the plane, taking each pixel from where `source` maps it.
+    fn warp<F: Fn(usize, usize) -> (f64, f64)>(&mut self, source: F) {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (sx, sy) = source(x, y);
+                pixels.push(self.sample(sx, sy));
+            }
+        }
+        self.pixels = pixels;
+    }
+
+    /// Warps by the inverse of the linear map `m` about the centre, offset by
+    /// `(dx, dy)`.
+    fn warp_affine(&mut self, m: [[f64; 2]; 2], dx: f64, dy: f64) {
+        let (cx, cy) = ((self.width as f64 - 1.0) / 2.0, (self.height as f64 - 1.0) / 2.0);
+        let det = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+        let inv = [[m[1][1] / det, -m[0][1] / det], [-m[1][0] / det, m[0][0] / det]];
+        self.warp(|x, y| {
+            let (u, v) = (x as f64 - cx - dx, y as f64 - cy - dy);
+            (inv[0][0] * u + inv[0][1] * v + cx, inv[1][0] * u + inv[1][1] * v + cy)
+        });
+    }
+}
+
+impl Augmentation {
+    /// A randomly transformed copy of `item`.
+    pub fn apply<T: Pixel, R: Rng>(&self, item: &Item<T>, rng: &mut R) -> Item<T> {
+        transform(item, |plane| self.apply_plane(plane, rng))
+    }
+
+    fn apply_plane<R: Rng>(&self, plane: &mut Plane, rng: &mut R) {
+        let symmetric = |max: f64, rng: &mut R| if max > 0.0 { rng.gen_range(-max, max) } else { 0.0 };
+        match *self {
+            Augmentation::Translate { max } => {
+                let (dx, dy) = (symmetric(max, rng), symmetric(max, rng));
+                plane.warp_affine([[1.0, 0.0], [0.0, 1.0]], dx, dy);
+            },
+            Augmentation::Rotate { max_degrees } => {
+                let angle = symmetric(max_degrees, rng) * PI / 180.0;
+                let (sin, cos) = angle.sin_cos();
+                plane.warp_affine([[cos, -sin], [sin, cos]], 0.0, 0.0);
+            },
+            Augmentation::Scale { min, max } => {
+                let s = if max > min { rng.gen_range(min, max) } else { min };
+                plane.warp_affine([[s, 0.0], [0.0, s]], 0.0, 0.0);
+            },
+            Augmentation::Shear { max } => {
+                let k = symmetric(max, rng);
+                plane.warp_affine([[1.0, k], [0.0, 1.0]], 0.0, 0.0);
+            },
+            Augmentation::Elastic { alpha, sigma } => {
+                let (w, h) = (plane.width, plane.height);
+                let mut field = || {
+                    let noise: Vec<f64> = (0..w * h).map(|_| rng.gen_range(-1.0, 1.0)).collect();
+                    let mut smooth = gaussian_blur(&noise, w, h, sigma);
+                    for d in &mut smooth {
+                        *d *= alpha;
+                    }
+                    smooth
+                };
+                let (dx, dy) = (field(), field());
+                plane.warp(|x, y| (x as f64 + dx[y * w + x], y as f64 + dy[y * w + x]));
+            },
+            Augmentation::Noise { std_dev } => {
+                let normal = Normal::new(0.0, std_dev);
+                for p in &mut plane.pixels {
+                    *p += normal.ind_sample(rng);
+                }
+            },
+            Augmentation::Erase { probability, min_area, max_area } => {
+                if rng.gen::<f64>() >= probability {
+                    return;
+                }
+                let (w, h) = (plane.width, plane.height);
+                let area = (w * h) as f64 * if max_area > min_area {
+                    rng.gen_range(min_area, max_area)
+                } else {
+                    min_area
+                };
+                // Aspect ratios between 0.3 and 1/0.3, as in Zhong et al.
+                let aspect = rng.gen_range(0.3f64.ln(), (1.0f64 / 0.3).ln()).exp();
+                let eh = ((area * aspect).sqrt().round() as usize).clamp(1, h);
+                let ew = ((area / aspect).sqrt().round() as usize).clamp(1, w);
+                let (x0, y0) = (rng.gen_range(0, w - ew + 1), rng.gen_range(0, h - eh + 1));
+                for y in y0..y0 + eh {
+                    for p in &mut plane.pixels[y * w + x0..y * w + x0 + ew] {
+                        *p = 0.0;
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Runs `f` over every `height x width` plane of `item`. Items with fewer
+/// than two dimensions are treated as a single row.
+fn transform<T: Pixel, F: FnMut(&mut Plane)>(item: &Item<T>, mut f: F) -> Item<T> {
+    let width = item.width().unwrap_or(1) as usize;
+    let height = item.height().unwrap_or(1) as usize;
+    let mut out = item.clone();
+    if width * height == 0 {
+        return out;
+    }
+    for chunk in out.data_mut().chunks_mut(width * height) {
+        let mut plane = Plane {
+            width,
+            height,
+            pixels: chunk.iter().map(|&p| p.to_intensity()).collect(),
+        };
+        f(&mut plane);
+        for (p, &x) in chunk.iter_mut().zip(&plane.pixels) {
+            *p = T::from_intensity(x);
+        }
+    }
+    out
+}
+
+/// Separable Gaussian blur, treating pixels outside the image as zero.
+fn gaussian_blur(pixels: &[f64], width: usize, height: usize, sigma: f64) -> Vec<f64> {
+    if sigma <= 0.0 {
+        return pixels.to_vec();
+    }
+    let radius = (3.0 * sigma).ceil() as isize;
+    let kernel: Vec<f64> = (-radius..=radius)
+        .map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f64 = kernel.iter().sum();
+
+    let blur = |input: &[f64], horizontal: bool| {
+        let mut output = vec![0.0; input.len()];
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let mut sum = 0.0;
+                for (k, weight) in kernel.iter().enumerate() {
+                    let offset = k as isize - radius;
+                    let (sx, sy) = if horizontal { (x + offset, y) } else { (x, y + offset) };
+                    if sx >= 0 && sy >= 0 && sx < width as isize && sy < height as isize {
+                        sum += weight * input[sy as usize * width + sx as usize];
+                    }
+                }
+                output[y as usize * width + x as usize] = sum / total;
+            }
+        }
+        output
+    };
+    blur(&blur(pixels, true), false)
+}
+
+/// Applies a sequence of augmentations, drawing from its own random number
+/// generator so that a seed fixes every transformation it makes.
+#[derive(Clone, Debug)]
+pub struct Augmenter<R: Rng> {
+    pub augmentations: Vec<Augmentation>,
+    rng: R,
+}
+
+impl Augmenter<StdRng> {
+    pub fn seeded(augmentations: Vec<Augmentation>, seed: usize) -> Augmenter<StdRng> {
+        Augmenter::new(augmentations, StdRng::from_seed(&[seed]))
+    }
+}
+
+impl<R: Rng> Augmenter<R> {
+    pub fn new(augmentations: Vec<Augmentation>, rng: R) -> Augmenter<R> {
+        Augmenter { augmentations, rng }
+    }
+
+    /// A copy of `item` with every augmentation applied in order.
+    pub fn augment<T: Pixel>(&mut self, item: &Item<T>) -> Item<T> {
+        let (augmentations, rng) = (&self.augmentations, &mut self.rng);
+        transform(item, |plane| {
+            for augmentation in augmentations {
+                augmentation.apply_plane(plane, rng);
+            }
+        })
+    }
+
+    /// `items` followed by `copies` rounds of augmented versions of them.
+    pub fn expand<T: Pixel>(&mut self, items: &[Item<T>], copies: usize) -> Vec<Item<T>> {
+        let mut expanded = items.to_vec();
+        for _ in 0..copies {
+            for item in items {
+                expanded.push(self.augment(item));
+            }
+        }
+        expanded
+    }
+
+    /// Like `expand`, keeping each augmented image's label.
+    pub fn expand_dataset(&mut self, data: &Dataset, copies: usize) -> Dataset {
+        let images = self.expand(data.images(), copies);
+        let labels = data.labels().iter().cloned().cycle()
+            .take(data.len() * (copies + 1))
+            .collect();
+        Dataset::from_parts(images, labels).expect("augmentation keeps labels valid")
+    }
+}
+
+/// A data set whose images are augmented afresh every time a batch copies
+/// them, so each epoch sees different variations.
+#[derive(Debug)]
+pub struct Augmented<'a, R: Rng> {
+    data: &'a Dataset,
+    augmenter: RefCell<Augmenter<R>>,
+}
+
+impl<'a, R: Rng> Augmented<'a, R> {
+    pub fn new(data: &'a Dataset, augmenter: Augmenter<R>) -> Augmented<'a, R> {
+        Augmented { data, augmenter: RefCell::new(augmenter) }
+    }
+}
+
+impl<'a, R: Rng> Examples for Augmented<'a, R> {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+    fn input_size(&self) -> usize {
+        Examples::input_size(self.data)
+    }
+    fn target_size(&self) -> usize {
+        NUM_CLASSES
+    }
+    fn write_input(&self, index: usize, out: &mut [f64]) {
+        let image = self.augmenter.borrow_mut().augment(self.data.image(index));
+        for (o, &x) in out.iter_mut().zip(image.data()) {
+            *o = x.to_intensity();
+        }
+    }
+    fn write_target(&self, index: usize, out: &mut [f64]) {
+        self.data.write_target(index, out);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseAugmentationError(String);
+
+impl FromStr for Augmentation {
+    type Err = ParseAugmentationError;
+
+    /// Parses `translate:MAX`, `rotate:DEGREES`, `scale:MIN:MAX`, `shear:MAX`,
+    /// `elastic:ALPHA:SIGMA`, `noise:STD_DEV` or `erase:P[:MIN_AREA:MAX_AREA]`.
+    fn from_str(s: &str) -> Result<Augmentation, ParseAugmentationError> {
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or("");
+        let args = parts
+            .map(|a| a.parse::<f64>().ok().filter(|x| x.is_finite() && *x >= 0.0)
+                 .ok_or_else(|| ParseAugmentationError(format!("invalid argument '{}'", a))))
+            .collect::<Result<Vec<f64>, _>>()?;
+        let expect = |counts: &[usize]| if counts.contains(&args.len()) {
+            Ok(())
+        } else {
+            Err(ParseAugmentationError(format!("wrong number of arguments for '{}'", name)))
+        };
+
+        let check = |ok: bool, message: &str| if ok {
+            Ok(())
+        } else {
+            Err(ParseAugmentationError(format!("'{}': {}", s, message)))
+        };
+
+        match name {
+            "translate" => expect(&[1]).map(|_| Augmentation::Translate { max: args[0] }),
+            "rotate" => expect(&[1]).map(|_| Augmentation::Rotate { max_degrees: args[0] }),
+            "scale" => {
+                expect(&[2])?;
+                check(args[0] > 0.0 && args[0] <= args[1], "scale needs 0 < MIN <= MAX")?;
+                Ok(Augmentation::Scale { min: args[0], max: args[1] })
+            },
+            "shear" => expect(&[1]).map(|_| Augmentation::Shear { max: args[0] }),
+            "elastic" => {
+                expect(&[2])?;
+                check(args[1] <= MAX_ELASTIC_SIGMA, 
+                      &format!("sigma can be at most {}", MAX_ELASTIC_SIGMA))?;
+                Ok(Augmentation::Elastic { alpha: args[0], sigma: args[1] })
+            },
+            "noise" => expect(&[1]).map(|_| Augmentation::Noise { std_dev: args[0] }),
+            "erase" => {
+                expect(&[1, 3])?;
+                let (min_area, max_area) = match args.len() {
+                    3 => (args[1], args[2]),
+                    _ => (0.02, 0.2),
+                };
+                check(args[0] <= 1.0, "the probability must be at most 1")?;
+                check(min_area > 0.0 && min_area <= max_area && max_area <= 1.0, 
+                      "erased areas need 0 < MIN <= MAX <= 1")?;
+                Ok(Augmentation::Erase { probability: args[0], min_area, max_area })
+            },
+            _ => Err(ParseAugmentationError(format!("unknown augmentation '{}'", name))),
+        }
+    }
+}
+
+impl fmt::Display for ParseAugmentationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mnist::batch::MiniBatcher;
+    use mnist::dataset::fixture;
+
+    /// A 5x5 image with a single lit pixel at `(x, y)`.
+    fn dot(x: usize, y: usize) -> Item<u8> {
+        let mut pixels = vec![0; 25];
+        pixels[y * 5 + x] = 255;
+        Item::new(pixels, vec![5, 5])
+    }
+
+    #[test]
+    fn geometric_transforms() {
+        let mut rng = StdRng::from_seed(&[1]);
+        // A right angle either way moves the top middle pixel to the left or
+        // right middle.
+        let rotated = Augmentation::Rotate { max_degrees: 90.0 };
+        let turned = transform(&dot(2, 0), |plane| plane.warp_affine([[0.0, -1.0], [1.0, 0.0]], 0.0, 0.0));
+        assert_eq!(turned.data(), dot(4, 2).data());
+
+        let shifted = transform(&dot(2, 2), |plane| plane.warp_affine([[1.0, 0.0], [0.0, 1.0]], 1.0, -2.0));
+        assert_eq!(shifted.data(), dot(3, 0).data());
+
+        let scaled = transform(&dot(3, 2), |plane| plane.warp_affine([[2.0, 0.0], [0.0, 2.0]], 0.0, 0.0));
+        assert_eq!(scaled.data()[2 * 5 + 4], 255);
+
+        // Random transforms keep the geometry and don't brighten anything.
+        for augmentation in &[rotated, Augmentation::Shear { max: 0.3 },
+                              Augmentation::Translate { max: 1.5 },
+                              Augmentation::Elastic { alpha: 2.0, sigma: 1.0 }] {
+            let out = augmentation.apply(&dot(2, 2), &mut rng);
+            assert_eq!(out.dimensions(), &[5, 5]);
+            assert!(out.data().iter().map(|&p| p as u32).sum::<u32>() <= 255 * 2);
+        }
+    }
+
+    #[test]
+    fn noise_and_erasing() {
+        let mut rng = StdRng::from_seed(&[2]);
+        let bright = Item::new(vec![1.0f32; 100], vec![10, 10]);
+
+        let noisy = Augmentation::Noise { std_dev: 0.1 }.apply(&bright, &mut rng);
+        let mean = noisy.data().iter().map(|&p| p as f64).sum::<f64>() / 100.0;
+        assert!((mean - 1.0).abs() < 0.05 && noisy.data() != bright.data());
+
+        let erase = Augmentation::Erase { probability: 1.0, min_area: 0.2, max_area: 0.2 };
+        let erased = erase.apply(&bright, &mut rng).data().iter().filter(|&&p| p == 0.0).count();
+        assert!(erased > 5 && erased < 40, "{}", erased);
+        let never = Augmentation::Erase { probability: 0.0, min_area: 0.2, max_area: 0.2 };
+        assert_eq!(never.apply(&bright, &mut rng), bright);
+    }
+
+    #[test]
+    fn seeded_pipelines_and_expansion() {
+        let pipeline: Vec<Augmentation> = "rotate:15,elastic:3:1,noise:0.05".split(',')
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let items = vec![dot(1, 1), dot(3, 2)];
+        let expand = || Augmenter::seeded(pipeline.clone(), 4).expand(&items, 2);
+        let expanded = expand();
+        assert_eq!(expanded.len(), 6);
+        assert_eq!(&expanded[..2], &items[..]);
+        assert_ne!(expanded[2], items[0]);
+        assert_eq!(expanded, expand());
+
+        let data = fixture();
+        let bigger = Augmenter::seeded(pipeline.clone(), 1).expand_dataset(&data, 3);
+        assert_eq!(bigger.len(), 8);
+        assert_eq!(bigger.labels(), &[7, 3, 7, 3, 7, 3, 7, 3]);
+
+        // On the fly, each epoch draws new variations.
+        let augmented = Augmented::new(&data, Augmenter::seeded(pipeline, 1));
+        let mut batcher = MiniBatcher::seeded(2, 0);
+        let first = batcher.epoch(&augmented).next().unwrap();
+        let second = batcher.epoch(&augmented).next().unwrap();
+        assert_eq!(first.inputs.cols(), 2);
+        assert_ne!(first.inputs, second.inputs);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("scale:0.9:1.1".parse(), Ok(Augmentation::Scale { min: 0.9, max: 1.1 }));
+        assert_eq!("erase:0.5".parse(),
+                   Ok(Augmentation::Erase { probability: 0.5, min_area: 0.02, max_area: 0.2 }));
+        assert!("rotate".parse::<Augmentation>().is_err());
+        assert!("noise:-1".parse::<Augmentation>().is_err());
+        assert!("flip:1".parse::<Augmentation>().is_err());
+        assert!("scale:0:1.1".parse::<Augmentation>().is_err());
+        assert!("scale:1.2:1.1".parse::<Augmentation>().is_err());
+        assert!("erase:0.5:0.1:1.5".parse::<Augmentation>().is_err());
+        assert!("erase:0.5:0:0.2".parse::<Augmentation>().is_err());
+        assert!("erase:2".parse::<Augmentation>().is_err());
+        assert!("elastic:34:1000000".parse::<Augmentation>().is_err());
+        assert!("elastic:34:4".parse::<Augmentation>().is_ok());
+    }
+}
@@ -0,0 +1,308 @@
+use rand::{Rng, SeedableRng, StdRng};
+
+use math::Matrix;
+use super::dataset::{Dataset, NUM_CLASSES};
+use super::error::{MnistError, Result};
+
+/// A set of examples that can be copied into batch matrices one row at a time.
+pub trait Examples {
+    fn len(&self) -> usize;
+    fn input_size(&self) -> usize;
+    fn target_size(&self) -> usize;
+    fn write_input(&self, index: usize, out: &mut [f64]);
+    fn write_target(&self, index: usize, out: &mut [f64]);
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fails if some example doesn't have `input_size()` inputs and 
+    /// `target_size()` targets. Batches assume they all do.
+    fn check_sizes(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Catches ragged examples when an epoch starts rather than partway through.
+fn debug_check_sizes<E: Examples + ?Sized>(data: &E) {
+    if cfg!(debug_assertions) {
+        if let Err(err) = data.check_sizes() {
+            panic!("{}", err);
+        }
+    }
+}
+
+/// A mini-batch with one row per example, in the order given by `indices`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Batch {
+    pub inputs: Matrix<f64>,
+    pub targets: Matrix<f64>,
+    /// The position of each row's example in the data set.
+    pub indices: Vec<usize>,
+}
+
+/// Splits data sets into mini-batches, visiting the examples in a new
+/// random order every epoch.
+#[derive(Clone, Debug)]
+pub struct MiniBatcher<R: Rng> {
+    pub batch_size: usize,
+    /// Whether a final batch smaller than `batch_size` is skipped.
+    pub drop_last: bool,
+    pub shuffle: bool,
+    rng: R,
+}
+
+/// The batches of one epoch.
+#[derive(Debug)]
+pub struct Batches<'a, E: 'a + Examples + ?Sized> {
+    data: &'a E,
+    order: Vec<usize>,
+    batch_size: usize,
+    drop_last: bool,
+    position: usize,
+}
+
+impl MiniBatcher<StdRng> {
+    pub fn seeded(batch_size: usize, seed: usize) -> MiniBatcher<StdRng> {
+        MiniBatcher::new(batch_size, StdRng::from_seed(&[seed]))
+    }
+}
+
+impl<R: Rng> MiniBatcher<R> {
+    pub fn new(batch_size: usize, rng: R) -> MiniBatcher<R> {
+        assert!(batch_size > 0);
+        MiniBatcher {
+            batch_size,
+            drop_last: false,
+            shuffle: true,
+            rng,
+        }
+    }
+
+    /// Starts an epoch over `data`, reshuffling if `shuffle` is set.
+    pub fn epoch<'a, E: Examples + ?Sized>(&mut self, data: &'a E) -> Batches<'a, E> {
+        debug_check_sizes(data);
+        let mut order: Vec<usize> = (0..data.len()).collect();
+        if self.shuffle {
+            self.rng.shuffle(&mut order);
+        }
+        Batches {
+            data,
+            order,
+            batch_size: self.batch_size,
+            drop_last: self.drop_last,
+            position: 0,
+        }
+    }
+}
+
+impl<'a, E: Examples + ?Sized> Batches<'a, E> {
+    /// Every example of `data` in order, such as for evaluation.
+    pub fn in_order(data: &'a E, batch_size: usize) -> Batches<'a, E> {
+        assert!(batch_size > 0);
+        debug_check_sizes(data);
+        Batches {
+            data,
+            order: (0..data.len()).collect(),
+            batch_size,
+            drop_last: false,
+            position: 0,
+        }
+    }
+
+    fn batch(&self, indices: &[usize]) -> Batch {
+        let (input_size, target_size) = (self.data.input_size(), self.data.target_size());
+        let mut inputs = Matrix::zeros(indices.len(), input_size);
+        let mut targets = Matrix::zeros(indices.len(), target_size);
+        for (row, &index) in indices.iter().enumerate() {
+            self.data.write_input(index, inputs.row_mut(row));
+            self.data.write_target(index, targets.row_mut(row));
+        }
+        Batch { inputs, targets, indices: indices.to_vec() }
+    }
+}
+
+impl<'a, E: Examples + ?Sized> Iterator for Batches<'a, E> {
+    type Item = Batch;
+
+    fn next(&mut self) -> Option<Batch> {
+        let remaining = self.order.len() - self.position;
+        if remaining == 0 || (self.drop_last && remaining < self.batch_size) {
+            return None;
+        }
+        let end = self.position + remaining.min(self.batch_size);
+        let batch = self.batch(&self.order[self.position..end]);
+        self.position = end;
+        Some(batch)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.order.len() - self.position;
+        let batches = if self.drop_last {
+            remaining / self.batch_size
+        } else {
+            remaining.div_ceil(self.batch_size)
+        };
+        (batches, Some(batches))
+    }
+}
+
+impl<'a, E: Examples + ?Sized> ExactSizeIterator for Batches<'a, E> {}
+
+/// Normalized pixels as inputs and one-hot labels as targets. 
+/// `Dataset::from_parts` keeps the images all the same size.
+impl Examples for Dataset {
+    fn len(&self) -> usize {
+        Dataset::len(self)
+    }
+    fn input_size(&self) -> usize {
+        self.images().first().map_or(0, |image| image.data().len())
+    }
+    fn target_size(&self) -> usize {
+        NUM_CLASSES
+    }
+    fn write_input(&self, index: usize, out: &mut [f64]) {
+        for (o, &x) in out.iter_mut().zip(self.image(index).data()) {
+            *o = x as f64 / 255.0;
+        }
+    }
+    fn write_target(&self, index: usize, out: &mut [f64]) {
+        for o in out.iter_mut() {
+            *o = 0.0;
+        }
+        out[self.label(index) as usize] = 1.0;
+    }
+}
+
+/// `(input, target)` pairs, such as those from `Dataset::pairs`.
+impl Examples for [(Vec<f64>, Vec<f64>)] {
+    fn len(&self) -> usize {
+        <[_]>::len(self)
+    }
+    fn input_size(&self) -> usize {
+        self.first().map_or(0, |pair| pair.0.len())
+    }
+    fn target_size(&self) -> usize {
+        self.first().map_or(0, |pair| pair.1.len())
+    }
+    fn write_input(&self, index: usize, out: &mut [f64]) {
+        out.copy_from_slice(&self[index].0);
+    }
+    fn write_target(&self, index: usize, out: &mut [f64]) {
+        out.copy_from_slice(&self[index].1);
+    }
+    fn check_sizes(&self) -> Result<()> {
+        let expected = (self.input_size(), self.target_size());
+        match self.iter().position(|pair| (pair.0.len(), pair.1.len()) != expected) {
+            Some(index) => Err(MnistError::ExampleSizeMismatch { 
+                index, 
+                expected, 
+                found: (self[index].0.len(), self[index].1.len()),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Examples for Vec<(Vec<f64>, Vec<f64>)> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+    fn input_size(&self) -> usize {
+        self[..].input_size()
+    }
+    fn target_size(&self) -> usize {
+        self[..].target_size()
+    }
+    fn write_input(&self, index: usize, out: &mut [f64]) {
+        self[..].write_input(index, out);
+    }
+    fn write_target(&self, index: usize, out: &mut [f64]) {
+        self[..].write_target(index, out);
+    }
+    fn check_sizes(&self) -> Result<()> {
+        self[..].check_sizes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mnist::dataset::fixture;
+
+    fn pairs(n: usize) -> Vec<(Vec<f64>, Vec<f64>)> {
+        (0..n).map(|i| (vec![i as f64, -(i as f64)], vec![i as f64])).collect()
+    }
+
+    #[test]
+    fn batches_cover_every_example_once() {
+        let data = pairs(10);
+        let mut batcher = MiniBatcher::seeded(4, 1);
+        let batches: Vec<Batch> = batcher.epoch(&data[..]).collect();
+
+        assert_eq!(batches.iter().map(|b| b.inputs.rows()).collect::<Vec<_>>(), vec![4, 4, 2]);
+        let mut seen: Vec<usize> = batches.iter().flat_map(|b| b.indices.clone()).collect();
+        assert_ne!(seen, (0..10).collect::<Vec<_>>());
+        seen.sort();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+
+        for batch in &batches {
+            for (row, &i) in batch.indices.iter().enumerate() {
+                assert_eq!(batch.inputs.row(row), &data[i].0[..]);
+                assert_eq!(batch.targets.row(row), &data[i].1[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn drop_last_and_reshuffling() {
+        let data = pairs(10);
+        let mut batcher = MiniBatcher::seeded(4, 7);
+        batcher.drop_last = true;
+        let first = batcher.epoch(&data[..]);
+        assert_eq!(first.len(), 2);
+        let first: Vec<Vec<usize>> = first.map(|b| b.indices).collect();
+        let second: Vec<Vec<usize>> = batcher.epoch(&data[..]).map(|b| b.indices).collect();
+        assert_eq!(second.len(), 2);
+        assert_ne!(first, second);
+
+        // The same seed gives the same sequence of epochs.
+        let mut again = MiniBatcher::seeded(4, 7);
+        again.drop_last = true;
+        let replay: Vec<Vec<usize>> = again.epoch(&data[..]).map(|b| b.indices).collect();
+        assert_eq!(first, replay);
+    }
+
+    #[test]
+    fn ragged_pairs_are_caught() {
+        let mut data = pairs(4);
+        assert!(data.check_sizes().is_ok());
+        data[2].0.push(1.0);
+        match data.check_sizes() {
+            Err(MnistError::ExampleSizeMismatch { index: 2, expected: (2, 1), found: (3, 1) }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Example 3 has 2 inputs and 2 targets")]
+    fn ragged_pairs_fail_at_epoch_start() {
+        let mut data = pairs(4);
+        data[3].1.push(1.0);
+        MiniBatcher::seeded(2, 0).epoch(&data);
+    }
+
+    #[test]
+    fn dataset_rows_are_normalized_and_one_hot() {
+        let data = fixture();
+
+        let mut batcher = MiniBatcher::seeded(2, 0);
+        batcher.shuffle = false;
+        let batch = batcher.epoch(&data).next().unwrap();
+        assert_eq!(batch.inputs.as_slice(), &[0.0, 1.0, 0.2, 0.4]);
+        assert_eq!(batch.targets.row(0)[7], 1.0);
+        assert_eq!(batch.targets.row(1)[3], 1.0);
+        assert_eq!(batch.targets.as_slice().iter().sum::<f64>(), 2.0);
+    }
+}
@@ -0,0 +1,196 @@
+use std::path;
+use std::io::Read;
+
+use byteorder::ReadBytesExt;
+
+use super::error::{MnistError, Result};
+use super::idx::{ElementType, IdxHeader, IdxReader, IdxWriter, Item};
+
+pub const NUM_CLASSES: usize = 10;
+
+/// MNIST images paired with their labels.
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    images: Vec<Item<u8>>,
+    labels: Vec<u8>,
+}
+
+impl Dataset {
+    pub fn from_files(images: &path::Path, labels: &path::Path) -> Result<Dataset> {
+        Dataset::new(IdxReader::from_file(images)?, IdxReader::from_file(labels)?)
+    }
+
+    pub fn new<I, L>(images: IdxReader<I>, labels: IdxReader<L>) -> Result<Dataset>
+        where I: Read + ReadBytesExt,
+              L: Read + ReadBytesExt,
+    {
+        let num_images = images.dimensions().first().cloned().unwrap_or(0);
+        let num_labels = labels.dimensions().first().cloned().unwrap_or(0);
+        if num_images != num_labels {
+            return Err(MnistError::ItemCountMismatch { 
+                images: num_images, 
+                labels: num_labels,
+            });
+        }
+
+        let images = images.items::<u8>()?.collect::<Result<Vec<_>>>()?;
+        let labels = labels.elements::<u8>()?.collect::<Result<Vec<_>>>()?;
+        Dataset::from_parts(images, labels)
+    }
+
+    /// Pairs images already in memory with their labels. The images must all 
+    /// have the same dimensions.
+    pub fn from_parts(images: Vec<Item<u8>>, labels: Vec<u8>) -> Result<Dataset> {
+        if images.len() != labels.len() {
+            return Err(MnistError::ItemCountMismatch { 
+                images: images.len() as u32, 
+                labels: labels.len() as u32,
+            });
+        }
+        if let Some(&label) = labels.iter().find(|&&l| l as usize >= NUM_CLASSES) {
+            return Err(MnistError::InvalidLabel(label));
+        }
+        if let Some(first) = images.first() {
+            if let Some(image) = images.iter().find(|i| i.dimensions() != first.dimensions()) {
+                return Err(MnistError::ItemGeometryMismatch { 
+                    expected: first.dimensions().to_vec(), 
+                    found: image.dimensions().to_vec(),
+                });
+            }
+        }
+
+        Ok(
+            Dataset {
+                images,
+                labels,
+            }
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+    pub fn image(&self, index: usize) -> &Item<u8> {
+        &self.images[index]
+    }
+    pub fn label(&self, index: usize) -> u8 {
+        self.labels[index]
+    }
+    pub fn images(&self) -> &[Item<u8>] {
+        &self.images
+    }
+    pub fn labels(&self) -> &[u8] {
+        &self.labels
+    }
+
+    /// The normalized image and one-hot label at `index`.
+    pub fn pair(&self, index: usize) -> (Vec<f64>, Vec<f64>) {
+        (normalize(&self.images[index]), one_hot(self.labels[index]))
+    }
+    pub fn pairs(&self) -> Vec<(Vec<f64>, Vec<f64>)> {
+        (0..self.len()).map(|i| self.pair(i)).collect()
+    }
+
+    /// Writes the images and labels out as a pair of IDX files.
+    pub fn save(&self, images: &path::Path, labels: &path::Path) -> Result<()> {
+        // Without any images to go by, the image file gets MNIST's geometry.
+        let mut dims = vec![0];
+        dims.extend_from_slice(self.images.first().map_or(&[28, 28][..], |i| i.dimensions()));
+        let mut writer = IdxWriter::create(images, IdxHeader::new(ElementType::U8, dims))?;
+        for image in &self.images {
+            writer.write_item(image)?;
+        }
+        writer.finish()?;
+
+        let mut writer = IdxWriter::create(labels, IdxHeader::new(ElementType::U8, vec![0]))?;
+        writer.write_elements(&self.labels)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Scales pixel intensities from `0..255` to `[0, 1]`.
+pub fn normalize(image: &Item<u8>) -> Vec<f64> {
+    image.data().iter().map(|&x| x as f64 / 255.0).collect()
+}
+
+pub fn one_hot(label: u8) -> Vec<f64> {
+    let mut target = vec![0.0; NUM_CLASSES];
+    target[label as usize] = 1.0;
+    target
+}
+
+/// Two 1x2 images, `[0, 255]` labelled 7 and `[51, 102]` labelled 3, parsed 
+/// from IDX bytes.
+#[cfg(test)]
+pub fn fixture() -> Dataset {
+    use std::io::Cursor;
+
+    let images = vec![0, 0, 0x08, 3, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 255, 51, 102];
+    let labels = vec![0, 0, 0x08, 1, 0, 0, 0, 2, 7, 3];
+    Dataset::new(IdxReader::new(Cursor::new(images)).unwrap(), 
+                 IdxReader::new(Cursor::new(labels)).unwrap()).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+
+    fn reader(bytes: Vec<u8>) -> IdxReader<io::Cursor<Vec<u8>>> {
+        IdxReader::new(io::Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn pairs_images_with_labels() {
+        let data = fixture();
+
+        assert_eq!(data.len(), 2);
+        let (x, y) = data.pair(1);
+        assert_eq!(x, vec![0.2, 0.4]);
+        assert_eq!(y[3], 1.0);
+        assert_eq!(y.iter().sum::<f64>(), 1.0);
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let data = fixture();
+
+        let dir = ::std::env::temp_dir();
+        let id = ::std::process::id();
+        let image_path = dir.join(format!("neural_net_save_images_{}.idx", id));
+        let label_path = dir.join(format!("neural_net_save_labels_{}.idx", id));
+        data.save(&image_path, &label_path).unwrap();
+        let loaded = Dataset::from_files(&image_path, &label_path);
+        let _ = ::std::fs::remove_file(&image_path);
+        let _ = ::std::fs::remove_file(&label_path);
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.images(), data.images());
+        assert_eq!(loaded.labels(), data.labels());
+    }
+
+    #[test]
+    fn rejects_mixed_image_sizes() {
+        let images = vec![Item::new(vec![0, 1], vec![1, 2]), Item::new(vec![0, 1, 2], vec![1, 3])];
+        match Dataset::from_parts(images, vec![1, 2]) {
+            Err(MnistError::ItemGeometryMismatch { ref expected, ref found }) 
+                if expected == &[1, 2] && found == &[1, 3] => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_count_mismatch() {
+        let images = reader(vec![0, 0, 0x08, 3, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 1, 0, 1]);
+        let labels = reader(vec![0, 0, 0x08, 1, 0, 0, 0, 3, 7, 3, 1]);
+
+        match Dataset::new(images, labels) {
+            Err(MnistError::ItemCountMismatch { images: 2, labels: 3 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
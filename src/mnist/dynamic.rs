@@ -0,0 +1,177 @@
+use std::io::Read;
+
+use byteorder::ReadBytesExt;
+
+use super::error::Result;
+use super::idx::{ElementScalar, ElementType, IdxReader};
+
+/// The elements of an IDX file, typed by the `ElementType` found in its header.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IdxData {
+    U8(Vec<u8>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdxTensor {
+    pub data: IdxData,
+    pub dimensions: Vec<u32>,
+}
+
+impl<R: Read + ReadBytesExt> IdxReader<R> {
+    /// Reads every element of the file without the caller having to name the
+    /// element type up front.
+    pub fn read_all_dynamic(&mut self) -> Result<IdxTensor> {
+        let data = match self.element_type() {
+            ElementType::U8 => IdxData::U8(self.read_all()?),
+            ElementType::I8 => IdxData::I8(self.read_all()?),
+            ElementType::I16 => IdxData::I16(self.read_all()?),
+            ElementType::I32 => IdxData::I32(self.read_all()?),
+            ElementType::F32 => IdxData::F32(self.read_all()?),
+            ElementType::F64 => IdxData::F64(self.read_all()?),
+        };
+
+        Ok(
+            IdxTensor {
+                data,
+                dimensions: self.dimensions().to_vec(),
+            }
+        )
+    }
+
+    fn read_all<T: ElementScalar>(&mut self) -> Result<Vec<T>> {
+        let count = self.num_elems();
+        let elems = self.read_vec(count)?;
+        self.check_end()?;
+        Ok(elems)
+    }
+}
+
+impl IdxData {
+    pub fn element_type(&self) -> ElementType {
+        match *self {
+            IdxData::U8(_) => ElementType::U8,
+            IdxData::I8(_) => ElementType::I8,
+            IdxData::I16(_) => ElementType::I16,
+            IdxData::I32(_) => ElementType::I32,
+            IdxData::F32(_) => ElementType::F32,
+            IdxData::F64(_) => ElementType::F64,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            IdxData::U8(ref v) => v.len(),
+            IdxData::I8(ref v) => v.len(),
+            IdxData::I16(ref v) => v.len(),
+            IdxData::I32(ref v) => v.len(),
+            IdxData::F32(ref v) => v.len(),
+            IdxData::F64(ref v) => v.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Converts every element to `f64`. Every IDX element type is exactly 
+    /// representable, so this never loses information.
+    pub fn to_f64(&self) -> Vec<f64> {
+        match *self {
+            IdxData::U8(ref v) => v.iter().map(|&x| x as f64).collect(),
+            IdxData::I8(ref v) => v.iter().map(|&x| x as f64).collect(),
+            IdxData::I16(ref v) => v.iter().map(|&x| x as f64).collect(),
+            IdxData::I32(ref v) => v.iter().map(|&x| x as f64).collect(),
+            IdxData::F32(ref v) => v.iter().map(|&x| x as f64).collect(),
+            IdxData::F64(ref v) => v.clone(),
+        }
+    }
+
+    /// Converts every element to `f32`, or returns `None` if any `i32` or 
+    /// `f64` element cannot be represented exactly.
+    pub fn to_f32(&self) -> Option<Vec<f32>> {
+        match *self {
+            IdxData::U8(ref v) => Some(v.iter().map(|&x| x as f32).collect()),
+            IdxData::I8(ref v) => Some(v.iter().map(|&x| x as f32).collect()),
+            IdxData::I16(ref v) => Some(v.iter().map(|&x| x as f32).collect()),
+            IdxData::I32(ref v) => v.iter()
+                .map(|&x| {
+                    let y = x as f32;
+                    if y as f64 == x as f64 { Some(y) } else { None }
+                })
+                .collect(),
+            IdxData::F32(ref v) => Some(v.clone()),
+            IdxData::F64(ref v) => v.iter()
+                .map(|&x| {
+                    let y = x as f32;
+                    if y as f64 == x || x.is_nan() { Some(y) } else { None }
+                })
+                .collect(),
+        }
+    }
+
+    /// Converts integer elements to `f64`, scaled by the largest magnitude of 
+    /// their type so unsigned data lands in `[0, 1]` and signed data in 
+    /// `[-1, 1]`. Floating point data is passed through unchanged.
+    pub fn normalized_f64(&self) -> Vec<f64> {
+        let scale = match *self {
+            IdxData::U8(_) => u8::MAX as f64,
+            IdxData::I8(_) => -(i8::MIN as f64),
+            IdxData::I16(_) => -(i16::MIN as f64),
+            IdxData::I32(_) => -(i32::MIN as f64),
+            IdxData::F32(_) | IdxData::F64(_) => 1.0,
+        };
+        self.to_f64().into_iter().map(|x| x / scale).collect()
+    }
+    pub fn normalized_f32(&self) -> Vec<f32> {
+        self.normalized_f64().into_iter().map(|x| x as f32).collect()
+    }
+}
+
+impl IdxTensor {
+    pub fn element_type(&self) -> ElementType {
+        self.data.element_type()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+    use mnist::idx::{IdxHeader, IdxWriter};
+
+    fn encode<T: ElementScalar>(elems: &[T]) -> IdxReader<io::Cursor<Vec<u8>>> {
+        let header = IdxHeader::new(T::element_type(), vec![0, 2]);
+        let mut writer = IdxWriter::new(io::Cursor::new(Vec::new()), header).unwrap();
+        writer.write_elements(elems).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+        IdxReader::new(io::Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn reads_by_header_type() {
+        let tensor = encode(&[0u8, 255, 51, 102]).read_all_dynamic().unwrap();
+        assert_eq!(tensor.dimensions, vec![2, 2]);
+        assert_eq!(tensor.data, IdxData::U8(vec![0, 255, 51, 102]));
+        assert_eq!(tensor.data.normalized_f64(), vec![0.0, 1.0, 0.2, 0.4]);
+
+        let tensor = encode(&[-128i8, 64]).read_all_dynamic().unwrap();
+        assert_eq!(tensor.element_type(), ElementType::I8);
+        assert_eq!(tensor.data.normalized_f32(), vec![-1.0, 0.5]);
+
+        let tensor = encode(&[0.5f64, -2.0]).read_all_dynamic().unwrap();
+        assert_eq!(tensor.data.to_f64(), vec![0.5, -2.0]);
+    }
+
+    #[test]
+    fn lossless_f32_conversion() {
+        assert_eq!(IdxData::I16(vec![-3, 300]).to_f32(), Some(vec![-3.0, 300.0]));
+        assert_eq!(IdxData::I32(vec![16_777_216]).to_f32(), Some(vec![16_777_216.0]));
+        assert_eq!(IdxData::I32(vec![16_777_217]).to_f32(), None);
+        assert_eq!(IdxData::F64(vec![0.1]).to_f32(), None);
+        assert_eq!(IdxData::F64(vec![0.5]).to_f32(), Some(vec![0.5]));
+    }
+}
@@ -4,19 +4,63 @@ use std::result;
 use std::error::Error;
 use std::fmt::Display;
 
+use super::idx::ElementType;
+use super::source::CorruptArchive;
+
 #[derive(Debug)]
 pub enum MnistError {
     Io(io::Error),
-    InvalidFormat,
-    InvalidElementType,
-    Parse(),
+    /// The header did not start with two zero bytes.
+    InvalidMagic { offset: u64, found: u16 },
+    InvalidElementType { offset: u64, value: u8 },
+    /// The file ended inside the header, optionally while reading the size of 
+    /// the given dimension.
+    TruncatedHeader { offset: u64, dimension: Option<usize> },
+    ElementTypeMismatch { expected: ElementType, found: ElementType },
+    InvalidDimensionCount { expected_min: usize, found: usize },
+    TooManyDimensions { found: usize, max: usize },
+    /// The dimensions multiply out to more elements or bytes than can be 
+    /// addressed.
+    SizeOverflow(Vec<u32>),
+    /// Honouring the header would need a buffer larger than the configured 
+    /// limit.
+    AllocationLimit { requested: u64, limit: u64 },
+    ItemGeometryMismatch { expected: Vec<u32>, found: Vec<u32> },
+    /// A writer was finished with a number of elements that does not divide 
+    /// into whole items.
+    PartialItem { elements: u64, item_size: usize },
+    DimensionOverflow { dimension: usize, size: u64 },
+    /// The data ends on an item boundary after `found` of the `expected` 
+    /// payload bytes, so whole items are missing.
+    Truncated { expected: u64, found: u64 },
+    /// The data ends `found` bytes into item `index`, which needs `expected`.
+    TruncatedItem { index: u64, expected: u64, found: u64 },
+    /// The payload is at least `found` bytes long but the header only 
+    /// accounts for `expected`.
+    TrailingData { expected: u64, found: u64 },
+    ItemCountMismatch { images: u32, labels: u32 },
+    InvalidLabel(u8),
+    IndexOutOfRange { index: usize, len: usize },
+    /// Example `index` has a different `(input, target)` size from the others.
+    ExampleSizeMismatch { index: usize, expected: (usize, usize), found: (usize, usize) },
+    /// The parts of a split don't add up to the number of items.
+    SplitSizeMismatch { total: usize, len: usize },
+    InvalidFoldCount { folds: usize, len: usize },
+    /// A cross-validation fold has nothing to train or validate on.
+    EmptyFold(usize),
+    CorruptArchive(io::Error),
 }
 
 pub type Result<T> = result::Result<T, MnistError>;
 
 impl From<io::Error> for MnistError {
     fn from(error: io::Error) -> MnistError {
-        MnistError::Io(error)
+        let corrupt = error.get_ref().is_some_and(|inner| inner.is::<CorruptArchive>());
+        if corrupt {
+            MnistError::CorruptArchive(error)
+        } else {
+            MnistError::Io(error)
+        }
     }
 }
 
@@ -24,34 +68,73 @@ impl Display for MnistError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             MnistError::Io(ref err) => write!(f, "{}", err),
-            MnistError::InvalidFormat => 
-                write!(f, "{}", self.description()),
-            MnistError::InvalidElementType => 
-                write!(f, "{}", self.description()),
-            MnistError::Parse() =>
-                write!(f, "Parse error"),
+            MnistError::InvalidMagic { offset, found } => 
+                write!(f, "Invalid magic number 0x{:04x} at byte {}, expected 0x0000", 
+                       found, offset),
+            MnistError::InvalidElementType { offset, value } => 
+                write!(f, "Invalid type constant 0x{:02x} for idx elements at byte {}", 
+                       value, offset),
+            MnistError::TruncatedHeader { offset, dimension: Some(dim) } =>
+                write!(f, "File ends at byte {} while reading the size of dimension {}", 
+                       offset, dim),
+            MnistError::TruncatedHeader { offset, dimension: None } =>
+                write!(f, "File ends at byte {} inside the header", offset),
+            MnistError::ElementTypeMismatch { expected, found } =>
+                write!(f, "Requested {} elements but the file contains {}", expected, found),
+            MnistError::InvalidDimensionCount { expected_min, found } =>
+                write!(f, "Expected at least {} dimensions, found {}", expected_min, found),
+            MnistError::TooManyDimensions { found, max } =>
+                write!(f, "{} dimensions is more than the limit of {}", found, max),
+            MnistError::SizeOverflow(ref dims) =>
+                write!(f, "Dimensions {:?} describe more data than can be addressed", dims),
+            MnistError::AllocationLimit { requested, limit } =>
+                write!(f, "Reading would allocate {} bytes, more than the limit of {}", 
+                       requested, limit),
+            MnistError::ItemGeometryMismatch { ref expected, ref found } =>
+                write!(f, "Item has dimensions {:?} but the file expects {:?}", found, expected),
+            MnistError::PartialItem { elements, item_size } =>
+                write!(f, "{} elements do not make up whole items of {} elements", 
+                       elements, item_size),
+            MnistError::DimensionOverflow { dimension, size } =>
+                write!(f, "Dimension {} has size {}, which does not fit the format", 
+                       dimension, size),
+            MnistError::Truncated { expected, found } =>
+                write!(f, "File is truncated: the header describes {} bytes of data but only {} \
+                           are present", expected, found),
+            MnistError::TruncatedItem { index, expected, found } =>
+                write!(f, "File is truncated inside item {}: {} of its {} bytes are present", 
+                       index, found, expected),
+            MnistError::TrailingData { expected, found } =>
+                write!(f, "File has at least {} bytes of trailing data after the {} the header describes", 
+                       found - expected, expected),
+            MnistError::ItemCountMismatch { images, labels } =>
+                write!(f, "Image file has {} items but label file has {}", images, labels),
+            MnistError::InvalidLabel(label) =>
+                write!(f, "Label {} is not a valid digit class", label),
+            MnistError::IndexOutOfRange { index, len } =>
+                write!(f, "Item index {} is out of range for {} items", index, len),
+            MnistError::ExampleSizeMismatch { index, expected, found } =>
+                write!(f, "Example {} has {} inputs and {} targets, expected {} and {}", 
+                       index, found.0, found.1, expected.0, expected.1),
+            MnistError::SplitSizeMismatch { total, len } =>
+                write!(f, "Split sizes add up to {} but there are {} items", total, len),
+            MnistError::InvalidFoldCount { folds, len } =>
+                write!(f, "Cannot split {} items into {} folds, need between 2 and {}", 
+                       len, folds, len),
+            MnistError::EmptyFold(fold) =>
+                write!(f, "Fold {} has no training or no validation items", fold),
+            MnistError::CorruptArchive(ref err) =>
+                write!(f, "Corrupt gzip archive: {}", err),
         }
     }
 }
 
 impl Error for MnistError {
-    fn description(&self) -> &str {
-        match *self {
-            MnistError::Io(ref err) => err.description(),
-            MnistError::InvalidFormat => "Invalid format",
-            MnistError::InvalidElementType => 
-                "Invalid type constant for idx elements",
-            MnistError::Parse() => "Unable to parse",
-        }
-    }
-
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
             MnistError::Io(ref err) => Some(err),
-            MnistError::InvalidFormat => None,
-            MnistError::InvalidElementType => None,
-            MnistError::Parse() => None,
+            MnistError::CorruptArchive(ref err) => Some(err),
+            _ => None,
         }
     }
 }
-
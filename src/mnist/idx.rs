@@ -1,78 +1,132 @@
+use std::fmt;
 use std::path;
 use std::fs;
 use std::io;
-use std::io::Read;
+use std::io::{Read, Write, Seek, SeekFrom};
 use std::marker;
+use std::mem;
 use std::default::Default;
 
-use byteorder::{BigEndian, ReadBytesExt, ByteOrder};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt, ByteOrder};
 
 use super::error::{MnistError, Result};
+use super::source::FileSource;
 
 pub trait ElementScalar: Sized + Copy + Default {
-    fn is_elem_type_compatible(ty: ElementType) -> Result<()>;
+    fn element_type() -> ElementType;
     fn read_element<T: ByteOrder, R: Read + ReadBytesExt>(read: &mut R) -> Result<Self>;
-}
+    fn write_element<T: ByteOrder, W: Write + WriteBytesExt>(self, writer: &mut W) -> Result<()>;
+    /// Decodes `out.len()` elements from `bytes`, which must hold exactly 
+    /// that many encoded elements.
+    fn decode_slice<T: ByteOrder>(bytes: &[u8], out: &mut [Self]);
 
-impl ElementScalar for u8 {
     fn is_elem_type_compatible(ty: ElementType) -> Result<()> {
-        if ty == ElementType::U8 { Ok(()) } 
-        else { Err(MnistError::InvalidElementType) }
+        if ty == Self::element_type() { 
+            Ok(()) 
+        } else { 
+            Err(MnistError::ElementTypeMismatch { expected: Self::element_type(), found: ty }) 
+        }
     }
+}
 
+impl ElementScalar for u8 {
+    fn element_type() -> ElementType {
+        ElementType::U8
+    }
     fn read_element<T: ByteOrder, R: Read + ReadBytesExt>(reader: &mut R) -> Result<u8> {
         Ok(reader.read_u8()?)
     }
+    fn write_element<T: ByteOrder, W: Write + WriteBytesExt>(self, writer: &mut W) -> Result<()> {
+        Ok(writer.write_u8(self)?)
+    }
+    fn decode_slice<T: ByteOrder>(bytes: &[u8], out: &mut [u8]) {
+        out.copy_from_slice(bytes);
+    }
 }
 
 impl ElementScalar for i8 {
-    fn is_elem_type_compatible(ty: ElementType) -> Result<()> {
-        if ty == ElementType::I8 { Ok(()) } 
-        else { Err(MnistError::InvalidElementType) }
+    fn element_type() -> ElementType {
+        ElementType::I8
     }
     fn read_element<T: ByteOrder, R: Read + ReadBytesExt>(reader: &mut R) -> Result<i8> {
         Ok(reader.read_i8()?)
     }
+    fn write_element<T: ByteOrder, W: Write + WriteBytesExt>(self, writer: &mut W) -> Result<()> {
+        Ok(writer.write_i8(self)?)
+    }
+    fn decode_slice<T: ByteOrder>(bytes: &[u8], out: &mut [i8]) {
+        for (o, &b) in out.iter_mut().zip(bytes.iter()) {
+            *o = b as i8;
+        }
+    }
 }
 
 impl ElementScalar for i16 {
-    fn is_elem_type_compatible(ty: ElementType) -> Result<()> {
-        if ty == ElementType::I16 { Ok(()) } 
-        else { Err(MnistError::InvalidElementType) }
+    fn element_type() -> ElementType {
+        ElementType::I16
     }
     fn read_element<T: ByteOrder, R: Read + ReadBytesExt>(reader: &mut R) -> Result<i16> {
         Ok(reader.read_i16::<T>()?)
     }
+    fn write_element<T: ByteOrder, W: Write + WriteBytesExt>(self, writer: &mut W) -> Result<()> {
+        Ok(writer.write_i16::<T>(self)?)
+    }
+    fn decode_slice<T: ByteOrder>(bytes: &[u8], out: &mut [i16]) {
+        for (o, b) in out.iter_mut().zip(bytes.chunks_exact(2)) {
+            *o = T::read_i16(b);
+        }
+    }
 }
 impl ElementScalar for i32 {
-    fn is_elem_type_compatible(ty: ElementType) -> Result<()> {
-        if ty == ElementType::I32 { Ok(()) } 
-        else { Err(MnistError::InvalidElementType) }
+    fn element_type() -> ElementType {
+        ElementType::I32
     }
     fn read_element<T: ByteOrder, R: Read + ReadBytesExt>(reader: &mut R) -> Result<i32> {
         Ok(reader.read_i32::<T>()?)
     }
+    fn write_element<T: ByteOrder, W: Write + WriteBytesExt>(self, writer: &mut W) -> Result<()> {
+        Ok(writer.write_i32::<T>(self)?)
+    }
+    fn decode_slice<T: ByteOrder>(bytes: &[u8], out: &mut [i32]) {
+        for (o, b) in out.iter_mut().zip(bytes.chunks_exact(4)) {
+            *o = T::read_i32(b);
+        }
+    }
 }
 impl ElementScalar for f32 {
-    fn is_elem_type_compatible(ty: ElementType) -> Result<()> {
-        if ty == ElementType::F32 { Ok(()) } 
-        else { Err(MnistError::InvalidElementType) }
+    fn element_type() -> ElementType {
+        ElementType::F32
     }
     fn read_element<T: ByteOrder, R: Read + ReadBytesExt>(reader: &mut R) -> Result<f32> {
         Ok(reader.read_f32::<T>()?)
     }
+    fn write_element<T: ByteOrder, W: Write + WriteBytesExt>(self, writer: &mut W) -> Result<()> {
+        Ok(writer.write_f32::<T>(self)?)
+    }
+    fn decode_slice<T: ByteOrder>(bytes: &[u8], out: &mut [f32]) {
+        for (o, b) in out.iter_mut().zip(bytes.chunks_exact(4)) {
+            *o = T::read_f32(b);
+        }
+    }
 }
 impl ElementScalar for f64 {
-    fn is_elem_type_compatible(ty: ElementType) -> Result<()> {
-        if ty == ElementType::F64 { Ok(()) } 
-        else { Err(MnistError::InvalidElementType) }
+    fn element_type() -> ElementType {
+        ElementType::F64
     }
     fn read_element<T: ByteOrder, R: Read + ReadBytesExt>(reader: &mut R) -> Result<f64> {
         Ok(reader.read_f64::<T>()?)
     }
+    fn write_element<T: ByteOrder, W: Write + WriteBytesExt>(self, writer: &mut W) -> Result<()> {
+        Ok(writer.write_f64::<T>(self)?)
+    }
+    fn decode_slice<T: ByteOrder>(bytes: &[u8], out: &mut [f64]) {
+        for (o, b) in out.iter_mut().zip(bytes.chunks_exact(8)) {
+            *o = T::read_f64(b);
+        }
+    }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ElementType {
     U8 = 0x08,
     I8 = 0x09,
@@ -91,7 +145,7 @@ impl ElementType {
             0x0c => Ok(ElementType::I32),
             0x0d => Ok(ElementType::F32),
             0x0e => Ok(ElementType::F64),
-            _ => Err(MnistError::InvalidElementType),
+            _ => Err(MnistError::InvalidElementType { offset: 2, value: val }),
         }
     }
 
@@ -107,19 +161,52 @@ impl ElementType {
     }
 }
 
+impl fmt::Display for ElementType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ElementType::U8 => "u8",
+            ElementType::I8 => "i8",
+            ElementType::I16 => "i16",
+            ElementType::I32 => "i32",
+            ElementType::F32 => "f32",
+            ElementType::F64 => "f64",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct IdxHeader {
     pub elem_type: ElementType,
     pub dimension_sizes: Vec<u32>,
 }
 
+/// How far past the payload `IdxReader::check_end` reads to measure trailing 
+/// data.
+pub const TRAILING_SCAN_LIMIT: u64 = 1 << 16;
+
+/// Bounds a reader checks a header against before trusting it, so that a 
+/// hostile file is rejected rather than exhausting memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The most dimensions a header may declare.
+    pub max_dimensions: usize,
+    /// The largest single buffer, in bytes, a reader will allocate, such as 
+    /// one item or a whole file read at once.
+    pub max_allocation: u64,
+}
+
 #[derive(Debug)]
 pub struct IdxReader<R: Read + ReadBytesExt> {
     reader: R,
     header: IdxHeader,
+    limits: Limits,
+    /// Payload bytes read so far, used to tell a clean end of the data from 
+    /// a truncated file.
+    consumed: u64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Item<T> 
     where T: ElementScalar
 {
@@ -134,6 +221,9 @@ pub struct Elements<T, R>
 {
     reader: IdxReader<R>,
     elem_type: marker::PhantomData<T>,
+    finished: bool,
+    /// Reused for the raw bytes of every element.
+    scratch: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -143,133 +233,247 @@ pub struct Items<T, R>
 {
     reader: IdxReader<R>,
     elem_type: marker::PhantomData<T>,
+    finished: bool,
+}
+
+#[derive(Debug)]
+pub struct IdxWriter<W: Write + Seek> {
+    writer: W,
+    header: IdxHeader,
+    header_start: u64,
+    elems_written: u64,
 }
 
-impl IdxReader<io::BufReader<fs::File>> {
-    pub fn from_file(file_name: &path::Path) -> Result<IdxReader<io::BufReader<fs::File>>> {
-        const BUF_READER_CAPACITY: usize = (1 << 20);
+impl IdxReader<FileSource> {
+    /// Opens an IDX file, decompressing it on the fly if it is gzipped. The 
+    /// length of an uncompressed file is checked against its header up front; 
+    /// gzipped files are checked as they are read.
+    pub fn from_file(file_name: &path::Path) -> Result<IdxReader<FileSource>> {
+        IdxReader::from_file_with_limits(file_name, Limits::default())
+    }
 
-        let f = fs::File::open(file_name)?;
-        let mut reader = io::BufReader::with_capacity(BUF_READER_CAPACITY, f);
+    pub fn from_file_with_limits(file_name: &path::Path, limits: Limits) 
+        -> Result<IdxReader<FileSource>> 
+    {
+        let mut reader = FileSource::open(file_name)?;
         
-        let header = IdxReader::read_header(&mut reader)?;
+        let header = IdxReader::read_header_with_limits(&mut reader, &limits)?;
+        if !reader.is_compressed() {
+            let len = fs::metadata(file_name)?.len();
+            header.check_payload_len(len.saturating_sub(header.encoded_len()))?;
+        }
 
         Ok(
             IdxReader {
-                reader: reader,
-                header: header,
+                reader,
+                header,
+                limits,
+                consumed: 0,
             }
         )
     }
-
 }
 
 impl<R: Read + ReadBytesExt> IdxReader<R> {
-    pub fn new(mut reader: R) -> Result<IdxReader<R>> {
-        let header = IdxReader::read_header(&mut reader)?;
+    pub fn new(reader: R) -> Result<IdxReader<R>> {
+        IdxReader::with_limits(reader, Limits::default())
+    }
+
+    pub fn with_limits(mut reader: R, limits: Limits) -> Result<IdxReader<R>> {
+        let header = IdxReader::read_header_with_limits(&mut reader, &limits)?;
 
         Ok(
             IdxReader {
-                reader: reader,
-                header: header,
+                reader,
+                header,
+                limits,
+                consumed: 0,
             }
         )
     }
 
+    pub fn header(&self) -> &IdxHeader {
+        &self.header
+    }
     pub fn dimensions(&self) -> &[u32] {
         &self.header.dimension_sizes
     }
 
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
     pub fn num_elems(&self) -> usize {
-        let mut total = 1;
-        for size in &self.header.dimension_sizes {
-            total *= *size as usize;
-        }
-        total
+        self.header.num_elems()
     }
     pub fn element_type(&self) -> ElementType {
-        self.header.elem_type.clone()
+        self.header.elem_type
     }
     pub fn reader(&mut self) -> &mut R {
         &mut self.reader
     }
+    pub fn reader_ref(&self) -> &R {
+        &self.reader
+    }
     pub fn item_size(&self) -> usize {
-        let mut total = 1;
-        for size in &self.header.dimension_sizes[1..] {
-            total *= *size as usize
-        }
-        total
+        self.header.item_size()
     }
 
+    /// Reads the rest of the payload the header describes, as one buffer 
+    /// within the allocation limit. Anything after the payload is left for 
+    /// `check_end`.
     pub fn read_bytes_to_end(&mut self) -> Result<Vec<u8>> {
-        let mut bytes = Vec::new();
-        self.reader.read_to_end(&mut bytes)?;
+        let remaining = self.header.payload_len().saturating_sub(self.consumed);
+        self.limits.check_allocation(remaining)?;
 
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(remaining).read_to_end(&mut bytes)?;
+        self.consumed += bytes.len() as u64;
+        if (bytes.len() as u64) < remaining {
+            self.header.check_payload_len(self.consumed)?;
+        }
         Ok(bytes)
     }
 
+    /// Whether every payload byte the header describes has been read.
+    pub fn is_at_end(&self) -> bool {
+        self.consumed >= self.header.payload_len()
+    }
+
+    /// Checks that nothing follows the payload the header describes, once it 
+    /// has all been read. Trailing data is only scanned up to 
+    /// `TRAILING_SCAN_LIMIT` bytes, so a compressed file can't make this 
+    /// decompress without end.
+    pub fn check_end(&mut self) -> Result<()> {
+        let extra = io::copy(&mut (&mut self.reader).take(TRAILING_SCAN_LIMIT), &mut io::sink())?;
+        self.consumed += extra;
+        self.header.check_payload_len(self.consumed)
+    }
+
     pub fn read_elements<T>(&mut self, buf: &mut [T]) -> Result<()> 
         where T: ElementScalar 
     {
-        self.assert_elem_type_compatible::<T>();
-        for i in buf.iter_mut() {
-            let elem: T = self.read_element()?;
-            *i = elem;
+        self.read_elements_with(buf, &mut Vec::new())
+    }
+
+    /// Like `read_elements`, staging the raw bytes in `scratch` so that 
+    /// repeated small reads reuse one buffer.
+    fn read_elements_with<T>(&mut self, buf: &mut [T], scratch: &mut Vec<u8>) -> Result<()> 
+        where T: ElementScalar 
+    {
+        const CHUNK_BYTES: usize = 1 << 16;
+
+        T::is_elem_type_compatible(self.element_type())?;
+        let elem_size = self.element_type().size_in_bytes() as usize;
+        let chunk_elems = CHUNK_BYTES / elem_size;
+        let needed = chunk_elems.min(buf.len()) * elem_size;
+        if scratch.len() < needed {
+            scratch.resize(needed, 0);
+        }
+
+        for chunk in buf.chunks_mut(chunk_elems) {
+            let raw = &mut scratch[..chunk.len() * elem_size];
+            self.fill(raw)?;
+            T::decode_slice::<BigEndian>(raw, chunk);
         }
         Ok(())
     }
-    pub fn elements<T>(self) -> Elements<T, R>
+
+    /// Like `read_exact`, but reports running out of data before the end of 
+    /// the payload as truncation.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    self.consumed += filled as u64;
+                    return Err(match self.header.check_payload_len(self.consumed) {
+                        Err(e @ MnistError::Truncated { .. }) | 
+                        Err(e @ MnistError::TruncatedItem { .. }) => e,
+                        _ => io::Error::from(io::ErrorKind::UnexpectedEof).into(),
+                    });
+                },
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.consumed += filled as u64;
+        Ok(())
+    }
+    pub fn elements<T>(self) -> Result<Elements<T, R>>
+        where T: ElementScalar 
+    {
+        T::is_elem_type_compatible(self.element_type())?;
+        Ok(
+            Elements {
+                reader: self,
+                elem_type: marker::PhantomData,
+                finished: false,
+                scratch: Vec::new(),
+            }
+        )
+    }
+
+    /// Reads `count` elements into a new vector. The vector grows as data 
+    /// arrives, so a header that overstates how much data follows cannot 
+    /// force a large allocation up front.
+    pub fn read_vec<T>(&mut self, count: usize) -> Result<Vec<T>> 
         where T: ElementScalar 
     {
-        self.assert_elem_type_compatible::<T>();
-        Elements {
-            reader: self,
-            elem_type: marker::PhantomData,
+        const CHUNK_ELEMS: usize = 1 << 16;
+
+        T::is_elem_type_compatible(self.element_type())?;
+        self.limits.check_allocation((count as u64).saturating_mul(mem::size_of::<T>() as u64))?;
+        let mut elems = Vec::with_capacity(count.min(CHUNK_ELEMS));
+        while elems.len() < count {
+            let start = elems.len();
+            elems.resize(start + (count - start).min(CHUNK_ELEMS), T::default());
+            self.read_elements(&mut elems[start..])?;
         }
+        Ok(elems)
     }
 
     pub fn read_item<T>(&mut self) -> Result<Item<T>>
         where T: ElementScalar 
     {
-        let item_sizes = self.item_size();
-        let mut elems: Vec<T> = vec![T::default(); item_sizes];
-        self.read_elements(&mut elems[..])?;
+        let item_size = self.item_size();
+        let elems = self.read_vec(item_size)?;
 
         Ok(
             Item {
-                elems: elems,
+                elems,
                 dimension_sizes: self.get_item_geometry(),
             }
         )
     }
+    /// Reads the remaining items, failing if the file is truncated or has 
+    /// data after the last item.
     pub fn read_items_to_end<T>(&mut self, buf: &mut Vec<Item<T>>) -> Result<()>
         where T: ElementScalar 
     {
-        loop {
-            let item = self.read_item::<T>();
-            match item {
-                Ok(x) => {
-                    buf.push(x);
-                },
-                Err(MnistError::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
-                    break;
-                },
-                Err(e) => {
-                    return Err(e);
-                },
-            }
+        while !self.is_at_end() {
+            buf.push(self.read_item::<T>()?);
         }
-        return Ok(());
+        self.check_end()
     }
-    pub fn items<T>(self) -> Items<T, R> 
+    pub fn items<T>(self) -> Result<Items<T, R>> 
         where T: ElementScalar 
     {
-        self.assert_elem_type_compatible::<T>();
-        assert!(self.dimensions().len() > 1);
-        Items {
-            reader: self,
-            elem_type: marker::PhantomData,
+        T::is_elem_type_compatible(self.element_type())?;
+        if self.dimensions().len() < 2 {
+            return Err(MnistError::InvalidDimensionCount { 
+                expected_min: 2, 
+                found: self.dimensions().len(),
+            });
         }
+        Ok(
+            Items {
+                reader: self,
+                elem_type: marker::PhantomData,
+                finished: false,
+            }
+        )
     }
 
     fn get_item_geometry(&self) -> Vec<u32> {
@@ -279,41 +483,52 @@ impl<R: Read + ReadBytesExt> IdxReader<R> {
             _ => self.dimensions()[1..].to_vec(),
         }
     }
-    fn assert_elem_type_compatible<T>(&self) 
-        where T: ElementScalar
-    {
-        T::is_elem_type_compatible(self.element_type())
-            .expect("Requested element type does not match file contents");
+    /// Reads and validates a header against the default `Limits`.
+    pub fn read_header(reader: &mut R) -> Result<IdxHeader> {
+        IdxReader::read_header_with_limits(reader, &Limits::default())
     }
 
-    fn read_element<T: ElementScalar>(&mut self) -> Result<T> {
-        let element = T::read_element::<BigEndian, _>(&mut self.reader);
-
-        element
-    }
-    fn read_header(reader: &mut R) -> Result<IdxHeader> {
-        let zero = reader.read_u16::<BigEndian>()?;
+    pub fn read_header_with_limits(reader: &mut R, limits: &Limits) -> Result<IdxHeader> {
+        let zero = reader.read_u16::<BigEndian>()
+            .map_err(|e| header_error(e, 0, None))?;
 
         if zero != 0x0000 {
-            return Err(MnistError::InvalidFormat)
+            return Err(MnistError::InvalidMagic { offset: 0, found: zero })
         }
 
-        let elem_type = reader.read_u8()?;
+        let elem_type = reader.read_u8()
+            .map_err(|e| header_error(e, 2, None))?;
         let type_enum = ElementType::from_value(elem_type)?;
 
-        let num_dims = reader.read_u8()?;
+        let num_dims = reader.read_u8()
+            .map_err(|e| header_error(e, 3, None))?;
+        if num_dims as usize > limits.max_dimensions {
+            return Err(MnistError::TooManyDimensions { 
+                found: num_dims as usize, 
+                max: limits.max_dimensions,
+            });
+        }
         let mut dim_sizes = vec![0; num_dims as usize];
 
-        for i in 0..dim_sizes.len() {
-            dim_sizes[i] = reader.read_u32::<BigEndian>()?;
+        for (i, size) in dim_sizes.iter_mut().enumerate() {
+            *size = reader.read_u32::<BigEndian>()
+                .map_err(|e| header_error(e, 4 + 4 * i as u64, Some(i)))?;
         }
 
-        Ok(
-            IdxHeader {
-                elem_type: type_enum,
-                dimension_sizes: dim_sizes,
-            }
-        )
+        let header = IdxHeader {
+            elem_type: type_enum,
+            dimension_sizes: dim_sizes,
+        };
+        header.validate(limits)?;
+        Ok(header)
+    }
+}
+
+fn header_error(error: io::Error, offset: u64, dimension: Option<usize>) -> MnistError {
+    if error.kind() == io::ErrorKind::UnexpectedEof {
+        MnistError::TruncatedHeader { offset, dimension }
+    } else {
+        error.into()
     }
 }
 
@@ -324,18 +539,21 @@ impl<T, R> Iterator for Elements<T, R>
     type Item = Result<T>;
 
     fn next(&mut self) -> Option<Result<T>> {
-        let elem = self.reader.read_element::<T>();
-
-        match elem {
-            Ok(x) => Some(Ok(x)),
-            Err(MnistError::Io(e)) => 
-                if e.kind() == io::ErrorKind::UnexpectedEof {
-                    None
-                } else {
-                    Some(Err(e.into())) 
-                },
-            Err(e) => Some(Err(e)),
+        if self.finished {
+            return None;
+        }
+        if self.reader.is_at_end() {
+            self.finished = true;
+            return self.reader.check_end().err().map(Err);
+        }
 
+        let mut elem = [T::default()];
+        match self.reader.read_elements_with(&mut elem, &mut self.scratch) {
+            Ok(()) => Some(Ok(elem[0])),
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            },
         }
     }
 }
@@ -343,6 +561,14 @@ impl<T, R> Iterator for Elements<T, R>
 impl<T> Item<T>
     where T: ElementScalar 
 {
+    pub fn new(elems: Vec<T>, dimension_sizes: Vec<u32>) -> Item<T> {
+        let item = Item {
+            elems,
+            dimension_sizes,
+        };
+        assert_eq!(item.elems.len(), item.total_elements());
+        item
+    }
     pub fn data(&self) -> &[T] {
         &self.elems[..]
     }
@@ -353,7 +579,7 @@ impl<T> Item<T>
         &self.dimension_sizes[..]
     }
     pub fn width(&self) -> Option<u32> {
-        if self.dimension_sizes.len() > 0 {
+        if !self.dimension_sizes.is_empty() {
             Some(self.dimension_sizes[self.dimension_sizes.len()-1])
         } else {
             None
@@ -366,12 +592,9 @@ impl<T> Item<T>
             None
         }
     }
-    pub fn total_elements(&self) -> u32 {
-        let mut total = 1;
-        for i in self.dimension_sizes.iter() {
-            total *= *i;
-        }
-        total
+    /// The product of the item's dimensions, saturating at `usize::MAX`.
+    pub fn total_elements(&self) -> usize {
+        checked_product(&self.dimension_sizes).unwrap_or(usize::MAX)
     }
 }
 
@@ -382,22 +605,630 @@ impl<T, R> Iterator for Items<T, R>
     type Item = Result<Item<T>>;
 
     fn next(&mut self) -> Option<Result<Item<T>>> {
-        let item = self.reader.read_item::<T>();
-
-        match item {
-            Ok(i) => Some(Ok(i)),
-            Err(MnistError::Io(e)) => 
-                if e.kind() == io::ErrorKind::UnexpectedEof {
-                    None
-                } else {
-                    Some(Err(e.into())) 
-                },
-            Err(e) => Some(Err(e)),
+        if self.finished {
+            return None;
+        }
+        if self.reader.is_at_end() {
+            self.finished = true;
+            return self.reader.check_end().err().map(Err);
+        }
+
+        match self.reader.read_item::<T>() {
+            Ok(item) => Some(Ok(item)),
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
+
+impl fmt::Display for IdxHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [", self.elem_type)?;
+        for (i, size) in self.dimension_sizes.iter().enumerate() {
+            write!(f, "{}", size)?;
+            if i < self.dimension_sizes.len()-1 {
+                write!(f, " x ")?;
+            }
+        }
+        write!(f, "]")?;
+        Ok(())
+    }
+}
+
+impl IdxHeader {
+    pub fn new(elem_type: ElementType, dimension_sizes: Vec<u32>) -> IdxHeader {
+        IdxHeader {
+            elem_type,
+            dimension_sizes,
+        }
+    }
+
+    /// The leading dimension, i.e. the number of items in the file.
+    pub fn num_items(&self) -> usize {
+        self.dimension_sizes.first().map_or(0, |&n| n as usize)
+    }
+    /// The dimensions of a single item: every dimension after the leading one.
+    pub fn item_geometry(&self) -> &[u32] {
+        self.dimension_sizes.get(1..).unwrap_or(&[])
+    }
+    /// Elements per item. Like the other sizes this saturates rather than 
+    /// overflowing; `validate` rejects headers where that would happen.
+    pub fn item_size(&self) -> usize {
+        checked_product(self.item_geometry()).unwrap_or(usize::MAX)
+    }
+    pub fn num_elems(&self) -> usize {
+        checked_product(&self.dimension_sizes).unwrap_or(usize::MAX)
+    }
+
+    /// Checks that every size derived from the header fits in memory 
+    /// arithmetic and that a single item stays within `limits`.
+    pub fn validate(&self, limits: &Limits) -> Result<()> {
+        if self.dimension_sizes.len() > limits.max_dimensions {
+            return Err(MnistError::TooManyDimensions { 
+                found: self.dimension_sizes.len(), 
+                max: limits.max_dimensions,
+            });
+        }
+        let elem_size = self.elem_type.size_in_bytes() as u64;
+        let bytes = |dims: &[u32]| {
+            checked_product(dims).and_then(|n| (n as u64).checked_mul(elem_size))
+        };
+        match (bytes(&self.dimension_sizes), bytes(self.item_geometry())) {
+            (Some(_), Some(item_bytes)) => limits.check_allocation(item_bytes),
+            _ => Err(MnistError::SizeOverflow(self.dimension_sizes.clone())),
+        }
+    }
+
+    /// Size in bytes of the data that follows the header.
+    pub fn payload_len(&self) -> u64 {
+        self.dimension_sizes.iter()
+            .fold(self.elem_type.size_in_bytes() as u64, |total, &size| {
+                total.saturating_mul(size as u64)
+            })
+    }
+
+    /// Checks that `found` bytes of data are exactly what the header 
+    /// describes, telling apart missing items, a partial last item and 
+    /// trailing bytes.
+    pub fn check_payload_len(&self, found: u64) -> Result<()> {
+        let expected = self.payload_len();
+        let item_bytes = (self.item_size() as u64)
+            .saturating_mul(self.elem_type.size_in_bytes() as u64);
+        if found > expected {
+            Err(MnistError::TrailingData { expected, found })
+        } else if found < expected && !found.is_multiple_of(item_bytes) {
+            Err(MnistError::TruncatedItem { 
+                index: found / item_bytes, 
+                expected: item_bytes, 
+                found: found % item_bytes,
+            })
+        } else if found < expected {
+            Err(MnistError::Truncated { expected, found })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Size in bytes of the encoded header.
+    pub fn encoded_len(&self) -> u64 {
+        4 + 4 * self.dimension_sizes.len() as u64
+    }
+
+    pub fn write<W: Write + WriteBytesExt>(&self, writer: &mut W) -> Result<()> {
+        if self.dimension_sizes.len() > u8::MAX as usize {
+            return Err(MnistError::TooManyDimensions { 
+                found: self.dimension_sizes.len(), 
+                max: u8::MAX as usize,
+            });
+        }
+
+        writer.write_u16::<BigEndian>(0x0000)?;
+        writer.write_u8(self.elem_type as u8)?;
+        writer.write_u8(self.dimension_sizes.len() as u8)?;
+        for size in &self.dimension_sizes {
+            writer.write_u32::<BigEndian>(*size)?;
+        }
+        Ok(())
+    }
+}
+
+impl Limits {
+    pub fn check_allocation(&self, bytes: u64) -> Result<()> {
+        if bytes > self.max_allocation {
+            Err(MnistError::AllocationLimit { requested: bytes, limit: self.max_allocation })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for Limits {
+    /// Up to 8 dimensions and 1 GiB per allocation, far beyond what MNIST 
+    /// style datasets need.
+    fn default() -> Limits {
+        Limits { max_dimensions: 8, max_allocation: 1 << 30 }
+    }
+}
+
+fn checked_product(sizes: &[u32]) -> Option<usize> {
+    sizes.iter().try_fold(1usize, |total, &size| total.checked_mul(size as usize))
+}
+
+impl IdxWriter<io::BufWriter<fs::File>> {
+    pub fn create(file_name: &path::Path, header: IdxHeader) 
+        -> Result<IdxWriter<io::BufWriter<fs::File>>> 
+    {
+        let f = fs::File::create(file_name)?;
+        IdxWriter::new(io::BufWriter::new(f), header)
+    }
+}
+
+impl<W: Write + Seek> IdxWriter<W> {
+    /// Writes `header` and prepares to stream elements after it. The leading 
+    /// dimension is rewritten by `finish` to match the number of items 
+    /// actually written, so it may be left as zero here.
+    pub fn new(mut writer: W, header: IdxHeader) -> Result<IdxWriter<W>> {
+        let header_start = writer.stream_position()?;
+        header.write(&mut writer)?;
+
+        Ok(
+            IdxWriter {
+                writer,
+                header,
+                header_start,
+                elems_written: 0,
+            }
+        )
+    }
+
+    pub fn header(&self) -> &IdxHeader {
+        &self.header
+    }
+    pub fn element_type(&self) -> ElementType {
+        self.header.elem_type
+    }
+    /// Number of elements in each item, i.e. the product of all but the 
+    /// leading dimension.
+    pub fn item_size(&self) -> usize {
+        self.header.item_size()
+    }
+
+    pub fn write_element<T>(&mut self, elem: T) -> Result<()> 
+        where T: ElementScalar
+    {
+        T::is_elem_type_compatible(self.element_type())?;
+        elem.write_element::<BigEndian, _>(&mut self.writer)?;
+        self.elems_written += 1;
+        Ok(())
+    }
+    pub fn write_elements<T>(&mut self, elems: &[T]) -> Result<()> 
+        where T: ElementScalar
+    {
+        T::is_elem_type_compatible(self.element_type())?;
+        for elem in elems {
+            elem.write_element::<BigEndian, _>(&mut self.writer)?;
+        }
+        self.elems_written += elems.len() as u64;
+        Ok(())
+    }
+    pub fn write_item<T>(&mut self, item: &Item<T>) -> Result<()> 
+        where T: ElementScalar
+    {
+        if item.dimensions() != self.header.item_geometry() {
+            return Err(MnistError::ItemGeometryMismatch { 
+                expected: self.header.item_geometry().to_vec(),
+                found: item.dimensions().to_vec(),
+            });
         }
+        self.write_elements(item.data())
+    }
+
+    /// Patches the leading dimension with the number of items written and 
+    /// returns the underlying writer, positioned after the last element.
+    pub fn finish(mut self) -> Result<W> {
+        if self.header.dimension_sizes.is_empty() {
+            return Err(MnistError::InvalidDimensionCount { expected_min: 1, found: 0 });
+        }
+        let item_size = self.item_size() as u64;
+        if item_size == 0 || !self.elems_written.is_multiple_of(item_size) {
+            return Err(MnistError::PartialItem { 
+                elements: self.elems_written, 
+                item_size: self.item_size(),
+            });
+        }
+        let num_items = self.elems_written / item_size;
+        if num_items > u32::MAX as u64 {
+            return Err(MnistError::DimensionOverflow { dimension: 0, size: num_items });
+        }
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.header_start + 4))?;
+        self.writer.write_u32::<BigEndian>(num_items as u32)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        self.header.dimension_sizes[0] = num_items as u32;
+        Ok(self.writer)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{Rng, SeedableRng, StdRng};
+    use mnist::random_access::{RandomAccess, SeekReader};
+
+    #[test]
+    fn read_header() {
+        let bytes = vec![0x00, 0x00, 0x08, 0x02, 
+                         0x00, 0x00, 0x00, 0x02, 
+                         0x00, 0x00, 0x00, 0x03,
+                         1, 2, 3, 4, 5, 6];
+        let mut reader = IdxReader::new(io::Cursor::new(bytes)).unwrap();
+
+        assert_eq!(reader.element_type(), ElementType::U8);
+        assert_eq!(reader.dimensions(), &[2, 3]);
+        assert_eq!(reader.item_size(), 3);
+
+        let item = reader.read_item::<u8>().unwrap();
+        assert_eq!(item.data(), &[1, 2, 3]);
+    }
+
+    fn round_trip<T>(elems: &[T], item_dims: &[u32]) -> Vec<Item<T>> 
+        where T: ElementScalar
+    {
+        let mut dims = vec![0];
+        dims.extend_from_slice(item_dims);
+        let header = IdxHeader::new(T::element_type(), dims);
+
+        let mut writer = IdxWriter::new(io::Cursor::new(Vec::new()), header).unwrap();
+        writer.write_elements(elems).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let mut reader = IdxReader::new(io::Cursor::new(bytes)).unwrap();
+        let item_size = item_dims.iter().product::<u32>() as usize;
+        assert_eq!(reader.element_type(), T::element_type());
+        assert_eq!(reader.dimensions()[0] as usize, elems.len() / item_size);
+        assert_eq!(&reader.dimensions()[1..], item_dims);
+
+        let mut items = Vec::new();
+        reader.read_items_to_end(&mut items).unwrap();
+        items
+    }
+
+    #[test]
+    fn writer_round_trip_all_types() {
+        let items = round_trip(&[1u8, 2, 3, 255, 0, 7], &[3]);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].data(), &[255, 0, 7]);
+
+        let items = round_trip(&[-128i8, 127, 0, -1], &[2, 2]);
+        assert_eq!(items[0].data(), &[-128, 127, 0, -1]);
+        assert_eq!(items[0].dimensions(), &[2, 2]);
+
+        let items = round_trip(&[i16::MIN, -2, 300, i16::MAX], &[2]);
+        assert_eq!(items[1].data(), &[300, i16::MAX]);
+
+        let items = round_trip(&[i32::MIN, 65536, -70000, i32::MAX], &[1]);
+        assert_eq!(items.iter().map(|i| i.data()[0]).collect::<Vec<_>>(), 
+                   vec![i32::MIN, 65536, -70000, i32::MAX]);
+
+        let items = round_trip(&[1.5f32, -0.25, f32::MAX, f32::MIN_POSITIVE], &[4]);
+        assert_eq!(items[0].data(), &[1.5, -0.25, f32::MAX, f32::MIN_POSITIVE]);
+
+        let items = round_trip(&[0.1f64, -1e300, 3.0], &[3]);
+        assert_eq!(items[0].data(), &[0.1, -1e300, 3.0]);
+    }
+
+    #[test]
+    fn writer_round_trip_labels() {
+        let mut writer = IdxWriter::new(io::Cursor::new(Vec::new()), 
+                                        IdxHeader::new(ElementType::U8, vec![0])).unwrap();
+        for label in &[5u8, 0, 4, 1, 9] {
+            writer.write_element(*label).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+        assert_eq!(&bytes[..8], &[0, 0, 0x08, 1, 0, 0, 0, 5]);
+
+        let labels = IdxReader::new(io::Cursor::new(bytes)).unwrap()
+            .elements::<u8>().unwrap().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(labels, vec![5, 0, 4, 1, 9]);
+    }
+
+    #[test]
+    fn writer_rejects_mismatches() {
+        let header = IdxHeader::new(ElementType::U8, vec![0, 2]);
+        let mut writer = IdxWriter::new(io::Cursor::new(Vec::new()), header).unwrap();
+
+        match writer.write_element(1.0f32) {
+            Err(MnistError::ElementTypeMismatch { 
+                expected: ElementType::F32, found: ElementType::U8 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        match writer.write_item(&Item::new(vec![1u8, 2, 3], vec![3])) {
+            Err(MnistError::ItemGeometryMismatch { .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        writer.write_element(1u8).unwrap();
+        match writer.finish() {
+            Err(MnistError::PartialItem { elements: 1, item_size: 2 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    fn bulk_matches_per_element<T: ElementScalar>() {
+        let elem_size = T::element_type().size_in_bytes() as usize;
+        // More than one internal chunk, and not a multiple of it.
+        let count = 3 * (1 << 16) / elem_size + 7;
+        let mut state = 0x2545_f491u32;
+        let payload: Vec<u8> = (0..count * elem_size)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect();
+
+        let mut bytes = Vec::new();
+        IdxHeader::new(T::element_type(), vec![count as u32]).write(&mut bytes).unwrap();
+        bytes.extend_from_slice(&payload);
+        let mut bulk = vec![T::default(); count];
+        IdxReader::new(io::Cursor::new(bytes)).unwrap().read_elements(&mut bulk).unwrap();
+
+        let mut cursor = io::Cursor::new(&payload);
+        let mut encoded = Vec::new();
+        for elem in bulk {
+            let single = T::read_element::<BigEndian, _>(&mut cursor).unwrap();
+            let start = encoded.len();
+            single.write_element::<BigEndian, _>(&mut encoded).unwrap();
+            elem.write_element::<BigEndian, _>(&mut encoded).unwrap();
+            let (a, b) = encoded[start..].split_at(elem_size);
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn bulk_decoding_matches_per_element() {
+        bulk_matches_per_element::<u8>();
+        bulk_matches_per_element::<i8>();
+        bulk_matches_per_element::<i16>();
+        bulk_matches_per_element::<i32>();
+        bulk_matches_per_element::<f32>();
+        bulk_matches_per_element::<f64>();
+    }
+
+    #[test]
+    fn bulk_decoding_reports_truncation() {
+        let bytes = vec![0, 0, 0x0b, 1, 0, 0, 0, 3, 0, 1, 0, 2, 0];
+        let mut buf = [0i16; 3];
+        match IdxReader::new(io::Cursor::new(bytes)).unwrap().read_elements(&mut buf) {
+            Err(MnistError::TruncatedItem { index: 2, expected: 2, found: 1 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    /// A u8 file of three 2-element items, with `payload` as its data.
+    fn items_file(payload: &[u8]) -> IdxReader<io::Cursor<Vec<u8>>> {
+        let mut bytes = vec![0, 0, 0x08, 2, 0, 0, 0, 3, 0, 0, 0, 2];
+        bytes.extend_from_slice(payload);
+        IdxReader::new(io::Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn streaming_detects_truncation_and_trailing_data() {
+        let read_all = |payload: &[u8]| {
+            let mut items = Vec::new();
+            items_file(payload).read_items_to_end::<u8>(&mut items).map(|_| items.len())
+        };
+        assert_eq!(read_all(&[1, 2, 3, 4, 5, 6]).unwrap(), 3);
+        match read_all(&[1, 2, 3, 4]) {
+            Err(MnistError::Truncated { expected: 6, found: 4 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        match read_all(&[1, 2, 3, 4, 5]) {
+            Err(MnistError::TruncatedItem { index: 2, expected: 2, found: 1 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        match read_all(&[1, 2, 3, 4, 5, 6, 7]) {
+            Err(MnistError::TrailingData { expected: 6, found: 7 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // The iterators yield the items they can, then the error, then stop.
+        let items: Vec<_> = items_file(&[1, 2, 3]).items::<u8>().unwrap().collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap().data(), &[1, 2]);
+        assert!(items[1].is_err());
+
+        let labels = vec![0, 0, 0x08, 1, 0, 0, 0, 2, 7, 3, 9];
+        let elems: Vec<_> = IdxReader::new(io::Cursor::new(labels)).unwrap()
+            .elements::<u8>().unwrap().collect();
+        assert_eq!(elems.len(), 3);
+        match elems[2] {
+            Err(MnistError::TrailingData { expected: 2, found: 3 }) => (),
+            ref other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_file_checks_length() {
+        let path = ::std::env::temp_dir()
+            .join(format!("neural_net_truncated_{}.idx", ::std::process::id()));
+        fs::write(&path, [0, 0, 0x08, 2, 0, 0, 0, 3, 0, 0, 0, 2, 1, 2, 3]).unwrap();
+        let result = IdxReader::from_file(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(MnistError::TruncatedItem { index: 1, expected: 2, found: 1 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn type_and_dimension_errors() {
+        let bytes = vec![0, 0, 0x08, 1, 0, 0, 0, 2, 7, 3];
+
+        let reader = IdxReader::new(io::Cursor::new(bytes.clone())).unwrap();
+        match reader.elements::<f64>() {
+            Err(MnistError::ElementTypeMismatch { 
+                expected: ElementType::F64, found: ElementType::U8 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let reader = IdxReader::new(io::Cursor::new(bytes)).unwrap();
+        match reader.items::<u8>() {
+            Err(MnistError::InvalidDimensionCount { expected_min: 2, found: 1 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn header_errors_carry_offsets() {
+        let read = |bytes: Vec<u8>| IdxReader::new(io::Cursor::new(bytes)).unwrap_err();
+
+        match read(vec![0, 1, 0x08, 1]) {
+            MnistError::InvalidMagic { offset: 0, found: 1 } => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+        match read(vec![0, 0, 0x07, 1]) {
+            MnistError::InvalidElementType { offset: 2, value: 7 } => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+        match read(vec![0, 0, 0x08, 2, 0, 0, 0, 1, 0, 0]) {
+            MnistError::TruncatedHeader { offset: 8, dimension: Some(1) } => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    fn header_bytes(elem_type: u8, dims: &[u32]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, elem_type, dims.len() as u8];
+        for &d in dims {
+            bytes.write_u32::<BigEndian>(d).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn whole_payload_reads_are_bounded() {
+        let mut bytes = header_bytes(0x08, &[1000]);
+        bytes.extend(vec![7; 1000 + 2 * TRAILING_SCAN_LIMIT as usize]);
+
+        let limits = Limits { max_allocation: 500, ..Limits::default() };
+        let mut reader = IdxReader::with_limits(io::Cursor::new(bytes.clone()), limits).unwrap();
+        match reader.read_bytes_to_end() {
+            Err(MnistError::AllocationLimit { requested: 1000, limit: 500 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut reader = IdxReader::new(io::Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.read_bytes_to_end().unwrap().len(), 1000);
+        match reader.check_end() {
+            Err(MnistError::TrailingData { expected: 1000, found }) 
+                if found == 1000 + TRAILING_SCAN_LIMIT => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn hostile_headers() {
+        let read = |bytes: Vec<u8>| IdxReader::new(io::Cursor::new(bytes)).unwrap_err();
+
+        match read(header_bytes(0x0e, &[u32::MAX; 4])) {
+            MnistError::SizeOverflow(ref dims) if dims.len() == 4 => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+        // No data at all, but the item size alone overflows.
+        match read(header_bytes(0x08, &[0, u32::MAX, u32::MAX, u32::MAX])) {
+            MnistError::SizeOverflow(_) => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+        match read(header_bytes(0x0d, &[1, 1 << 16, 1 << 15])) {
+            MnistError::AllocationLimit { requested, limit } 
+                if requested == 4 << 31 && limit == 1 << 30 => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+        match read(vec![0, 0, 0x08, 200]) {
+            MnistError::TooManyDimensions { found: 200, max: 8 } => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let limits = Limits { max_dimensions: 3, max_allocation: 16 };
+        let mut reader = IdxReader::with_limits(
+            io::Cursor::new(header_bytes(0x08, &[1, 4, 4])), limits).unwrap();
+        assert!(reader.read_vec::<u8>(17).is_err());
+        // A huge item that passes the limit is only allocated as data arrives.
+        let mut bytes = header_bytes(0x08, &[1, 1 << 14, 1 << 15]);
+        bytes.extend_from_slice(&[1, 2, 3]);
+        match IdxReader::new(io::Cursor::new(bytes)).unwrap().read_item::<u8>() {
+            Err(MnistError::TruncatedItem { index: 0, found: 3, .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    /// Runs every read path over `bytes`; any outcome but a panic is fine.
+    fn exercise(bytes: &[u8], limits: Limits) {
+        let open = || IdxReader::with_limits(io::Cursor::new(bytes.to_vec()), limits);
+        if let Ok(mut reader) = open() {
+            let _ = reader.read_items_to_end::<u8>(&mut Vec::new());
+        }
+        if let Ok(Ok(elems)) = open().map(|r| r.elements::<u8>()) {
+            for elem in elems.take(1 << 16) {
+                if elem.is_err() {
+                    break;
+                }
+            }
+        }
+        if let Ok(Ok(items)) = open().map(|r| r.items::<i16>()) {
+            let _ = items.take(1 << 10).collect::<Vec<_>>();
+        }
+        if let Ok(mut reader) = open() {
+            let _ = reader.read_all_dynamic();
+        }
+        if let Ok(mut reader) = SeekReader::new(io::Cursor::new(bytes)) {
+            let _ = reader.read_item_at::<u8>(0);
+        }
+    }
+
+    #[test]
+    fn fuzzed_inputs_produce_errors_not_panics() {
+        let mut rng = StdRng::from_seed(&[22]);
+        let limits = Limits { max_dimensions: 6, max_allocation: 1 << 20 };
+        let mut valid = header_bytes(0x08, &[3, 2, 2]);
+        valid.extend(1..13);
+        let interesting = [0, 1, 2, 255, 256, 1 << 16, 1 << 31, u32::MAX - 1, u32::MAX];
+
+        for case in 0..3000 {
+            let bytes = match case % 3 {
+                // Corrupt a few bytes of a valid file, mostly in the header.
+                0 => {
+                    let mut bytes = valid.clone();
+                    for _ in 0..rng.gen_range(1, 4) {
+                        let end = if rng.gen() { 16 } else { bytes.len() };
+                        let i = rng.gen_range(0, end);
+                        bytes[i] = rng.gen();
+                    }
+                    bytes
+                },
+                // Cut off or extend a valid file.
+                1 => {
+                    let mut bytes = valid.clone();
+                    let len = rng.gen_range(0, 2 * valid.len());
+                    bytes.resize(len, rng.gen());
+                    bytes
+                },
+                // A made-up header with extreme sizes and a little data.
+                _ => {
+                    let dims: Vec<u32> = (0..rng.gen_range(0, 8))
+                        .map(|_| *rng.choose(&interesting).unwrap())
+                        .collect();
+                    let types = [0x08, 0x09, 0x0b, 0x0c, 0x0d, 0x0e, 0x00, 0xff];
+                    let mut bytes = header_bytes(*rng.choose(&types).unwrap(), &dims);
+                    let extra = rng.gen_range(0, 32);
+                    bytes.extend((0..extra).map(|_| rng.gen::<u8>()));
+                    bytes
+                },
+            };
+            exercise(&bytes, limits);
+        }
+    }
 }
@@ -1,2 +1,11 @@
 pub mod idx;
 pub mod error;
+pub mod source;
+pub mod dynamic;
+pub mod dataset;
+pub mod random_access;
+pub mod batch;
+pub mod split;
+pub mod augment;
+
+pub use self::dataset::Dataset;
@@ -0,0 +1,245 @@
+use std::fs;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path;
+
+use byteorder::BigEndian;
+use memmap2::Mmap;
+
+use super::error::{MnistError, Result};
+use super::idx::{ElementScalar, IdxHeader, IdxReader, Item, Limits};
+
+/// Item-level random access into an IDX file, so epochs can visit items in 
+/// shuffled order without loading the whole file.
+pub trait RandomAccess {
+    fn header(&self) -> &IdxHeader;
+    fn read_item_at<T: ElementScalar>(&mut self, index: usize) -> Result<Item<T>>;
+
+    fn len(&self) -> usize {
+        self.header().num_items()
+    }
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn item_size(&self) -> usize {
+        self.header().item_size()
+    }
+}
+
+/// Random access over any seekable source, reading one item per seek. The 
+/// length of the source is checked against the header when it is opened.
+#[derive(Debug)]
+pub struct SeekReader<R: Read + Seek> {
+    reader: R,
+    header: IdxHeader,
+    data_start: u64,
+    /// Reused for the raw bytes of each item read.
+    scratch: Vec<u8>,
+}
+
+/// Random access over a memory-mapped IDX file. Items are decoded straight 
+/// out of the mapping and the OS pages data in and out as needed.
+#[derive(Debug)]
+pub struct MmapReader {
+    mmap: Mmap,
+    header: IdxHeader,
+    data_start: usize,
+}
+
+impl SeekReader<io::BufReader<fs::File>> {
+    pub fn from_file(file_name: &path::Path) -> Result<SeekReader<io::BufReader<fs::File>>> {
+        SeekReader::from_file_with_limits(file_name, Limits::default())
+    }
+
+    pub fn from_file_with_limits(file_name: &path::Path, limits: Limits) 
+        -> Result<SeekReader<io::BufReader<fs::File>>> 
+    {
+        SeekReader::with_limits(io::BufReader::new(fs::File::open(file_name)?), limits)
+    }
+}
+
+impl<R: Read + Seek> SeekReader<R> {
+    pub fn new(reader: R) -> Result<SeekReader<R>> {
+        SeekReader::with_limits(reader, Limits::default())
+    }
+
+    pub fn with_limits(mut reader: R, limits: Limits) -> Result<SeekReader<R>> {
+        let header = IdxReader::read_header_with_limits(&mut reader, &limits)?;
+        let data_start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        header.check_payload_len(end.saturating_sub(data_start))?;
+
+        Ok(
+            SeekReader {
+                reader,
+                header,
+                data_start,
+                scratch: Vec::new(),
+            }
+        )
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> RandomAccess for SeekReader<R> {
+    fn header(&self) -> &IdxHeader {
+        &self.header
+    }
+
+    fn read_item_at<T: ElementScalar>(&mut self, index: usize) -> Result<Item<T>> {
+        T::is_elem_type_compatible(self.header.elem_type)?;
+        check_index(index, self.len())?;
+
+        let item_bytes = item_bytes(&self.header);
+        self.reader.seek(SeekFrom::Start(self.data_start + (index * item_bytes) as u64))?;
+
+        self.scratch.resize(item_bytes, 0);
+        self.reader.read_exact(&mut self.scratch)?;
+        Ok(decode_item(&self.header, &self.scratch))
+    }
+}
+
+impl MmapReader {
+    pub fn open(file_name: &path::Path) -> Result<MmapReader> {
+        MmapReader::open_with_limits(file_name, Limits::default())
+    }
+
+    pub fn open_with_limits(file_name: &path::Path, limits: Limits) -> Result<MmapReader> {
+        let file = fs::File::open(file_name)?;
+        // The mapping is only ever read, but another process truncating the 
+        // file underneath us would still fault. IDX datasets are treated as 
+        // immutable once written.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let mut bytes = &mmap[..];
+        let header = IdxReader::read_header_with_limits(&mut bytes, &limits)?;
+        let data_start = mmap.len() - bytes.len();
+        header.check_payload_len(bytes.len() as u64)?;
+
+        Ok(
+            MmapReader {
+                mmap,
+                header,
+                data_start,
+            }
+        )
+    }
+
+    /// The raw big-endian bytes of item `index`.
+    pub fn item_bytes(&self, index: usize) -> Result<&[u8]> {
+        check_index(index, self.len())?;
+
+        let size = item_bytes(&self.header);
+        let start = self.data_start + index * size;
+        self.mmap.get(start..start + size)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into())
+    }
+
+    pub fn item<T: ElementScalar>(&self, index: usize) -> Result<Item<T>> {
+        T::is_elem_type_compatible(self.header.elem_type)?;
+
+        Ok(decode_item(&self.header, self.item_bytes(index)?))
+    }
+}
+
+impl RandomAccess for MmapReader {
+    fn header(&self) -> &IdxHeader {
+        &self.header
+    }
+
+    fn read_item_at<T: ElementScalar>(&mut self, index: usize) -> Result<Item<T>> {
+        self.item(index)
+    }
+}
+
+/// Decodes one item from exactly its encoded bytes.
+fn decode_item<T: ElementScalar>(header: &IdxHeader, bytes: &[u8]) -> Item<T> {
+    let mut elems = vec![T::default(); header.item_size()];
+    T::decode_slice::<BigEndian>(bytes, &mut elems);
+    Item::new(elems, header.item_geometry().to_vec())
+}
+
+fn item_bytes(header: &IdxHeader) -> usize {
+    header.item_size() * header.elem_type.size_in_bytes() as usize
+}
+
+fn check_index(index: usize, len: usize) -> Result<()> {
+    if index < len {
+        Ok(())
+    } else {
+        Err(MnistError::IndexOutOfRange { index, len })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use mnist::idx::{ElementType, IdxWriter};
+
+    fn encode(elems: &[i16], item_dims: &[u32]) -> Vec<u8> {
+        let mut dims = vec![0];
+        dims.extend_from_slice(item_dims);
+        let mut writer = IdxWriter::new(io::Cursor::new(Vec::new()), 
+                                        IdxHeader::new(ElementType::I16, dims)).unwrap();
+        writer.write_elements(elems).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn check_items<A: RandomAccess>(reader: &mut A) {
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.read_item_at::<i16>(2).unwrap().data(), &[5, -6]);
+        assert_eq!(reader.read_item_at::<i16>(0).unwrap().data(), &[1, -2]);
+        assert_eq!(reader.read_item_at::<i16>(1).unwrap().dimensions(), &[2]);
+        assert!(reader.read_item_at::<i16>(3).is_err());
+        assert!(reader.read_item_at::<u8>(0).is_err());
+    }
+
+    #[test]
+    fn seek_reader() {
+        let bytes = encode(&[1, -2, 3, -4, 5, -6], &[2]);
+        check_items(&mut SeekReader::new(io::Cursor::new(bytes)).unwrap());
+    }
+
+    #[test]
+    fn seek_reader_checks_length() {
+        let mut bytes = encode(&[1, -2, 3, -4, 5, -6], &[2]);
+        bytes.truncate(bytes.len() - 4);
+        match SeekReader::new(io::Cursor::new(bytes)) {
+            Err(MnistError::Truncated { expected: 12, found: 8 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn readers_take_limits() {
+        let bytes = encode(&[1, -2, 3, -4, 5, -6], &[2]);
+        let limits = Limits { max_allocation: 2, ..Limits::default() };
+        match SeekReader::with_limits(io::Cursor::new(bytes.clone()), limits) {
+            Err(MnistError::AllocationLimit { requested: 4, limit: 2 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let path = env::temp_dir().join(format!("neural_net_mmap_limits_{}.idx", ::std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let result = MmapReader::open_with_limits(&path, limits);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(MnistError::AllocationLimit { requested: 4, limit: 2 }) => (),
+            other => panic!("unexpected result {:?}", other.map(|r| r.len())),
+        }
+    }
+
+    #[test]
+    fn mmap_reader() {
+        let path = env::temp_dir().join(format!("neural_net_mmap_{}.idx", ::std::process::id()));
+        fs::write(&path, encode(&[1, -2, 3, -4, 5, -6], &[2])).unwrap();
+
+        let result = MmapReader::open(&path).map(|mut reader| check_items(&mut reader));
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Read};
+use std::path;
+
+use flate2::bufread::GzDecoder;
+
+const BUF_READER_CAPACITY: usize = 1 << 20;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A file opened for reading IDX data, transparently decompressing it when 
+/// it starts with the gzip magic bytes.
+pub enum FileSource {
+    Raw(io::BufReader<fs::File>),
+    Gzip(io::BufReader<GzDecoder<io::BufReader<fs::File>>>),
+}
+
+/// Marks an `io::Error` as coming from a damaged gzip stream rather than the 
+/// underlying file, so it can be reported as `MnistError::CorruptArchive`.
+#[derive(Debug)]
+pub struct CorruptArchive(io::Error);
+
+impl FileSource {
+    pub fn open(file_name: &path::Path) -> io::Result<FileSource> {
+        let f = fs::File::open(file_name)?;
+        let mut reader = io::BufReader::with_capacity(BUF_READER_CAPACITY, f);
+
+        if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            let decoder = GzDecoder::new(reader);
+            Ok(FileSource::Gzip(io::BufReader::with_capacity(BUF_READER_CAPACITY, decoder)))
+        } else {
+            Ok(FileSource::Raw(reader))
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        match *self {
+            FileSource::Raw(_) => false,
+            FileSource::Gzip(_) => true,
+        }
+    }
+}
+
+impl Read for FileSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            FileSource::Raw(ref mut reader) => reader.read(buf),
+            FileSource::Gzip(ref mut reader) => reader.read(buf).map_err(|e| {
+                match e.kind() {
+                    io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => 
+                        io::Error::new(io::ErrorKind::InvalidData, CorruptArchive(e)),
+                    _ => e,
+                }
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for FileSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileSource::Raw(_) => write!(f, "FileSource::Raw"),
+            FileSource::Gzip(_) => write!(f, "FileSource::Gzip"),
+        }
+    }
+}
+
+impl fmt::Display for CorruptArchive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for CorruptArchive {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::io;
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    use mnist::error::MnistError;
+    use mnist::idx::{ElementType, IdxHeader, IdxReader, IdxWriter, Item};
+
+    fn gzipped_idx() -> Vec<u8> {
+        let mut writer = IdxWriter::new(io::Cursor::new(Vec::new()), 
+                                        IdxHeader::new(ElementType::U8, vec![0, 2, 2])).unwrap();
+        writer.write_elements(&(0..64).collect::<Vec<u8>>()).unwrap();
+        let raw = writer.finish().unwrap().into_inner();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_file(name: &str, bytes: &[u8]) -> Result<Vec<Item<u8>>, MnistError> {
+        let path = env::temp_dir().join(format!("neural_net_{}_{}.gz", name, ::std::process::id()));
+        fs::write(&path, bytes).unwrap();
+
+        let result = IdxReader::from_file(&path).and_then(|mut reader| {
+            assert!(reader.reader().is_compressed());
+            let mut items = Vec::new();
+            reader.read_items_to_end(&mut items).map(|_| items)
+        });
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn reads_gzip_files() {
+        let items = read_file("gzip_ok", &gzipped_idx()).unwrap();
+        assert_eq!(items.len(), 16);
+        assert_eq!(items[15].data(), &[60, 61, 62, 63]);
+    }
+
+    #[test]
+    fn reports_corrupt_archives() {
+        let mut bytes = gzipped_idx();
+        let len = bytes.len();
+        // Damage the trailing CRC so the stream decodes but fails verification.
+        bytes[len - 6] ^= 0xff;
+
+        match read_file("gzip_corrupt", &bytes) {
+            Err(MnistError::CorruptArchive(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use rand::Rng;
+
+use super::batch::Examples;
+use super::dataset::Dataset;
+use super::error::{MnistError, Result};
+use super::idx::Item;
+
+/// Some of the examples of a data set, picked by index without copying them.
+#[derive(Debug)]
+pub struct Subset<'a, E: 'a + ?Sized> {
+    data: &'a E,
+    indices: &'a [usize],
+}
+
+/// One round of k-fold cross-validation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fold {
+    pub train: Vec<usize>,
+    /// The held-out fold.
+    pub validation: Vec<usize>,
+}
+
+impl<'a, E: Examples + ?Sized> Subset<'a, E> {
+    /// Fails if any of `indices` is out of range for `data`.
+    pub fn new(data: &'a E, indices: &'a [usize]) -> Result<Subset<'a, E>> {
+        let len = data.len();
+        match indices.iter().find(|&&i| i >= len) {
+            Some(&index) => Err(MnistError::IndexOutOfRange { index, len }),
+            None => Ok(Subset { data, indices }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+    /// The position of each example in the underlying data set.
+    pub fn indices(&self) -> &'a [usize] {
+        self.indices
+    }
+    pub fn data(&self) -> &'a E {
+        self.data
+    }
+
+    /// Copies the examples out as `(input, target)` pairs.
+    pub fn pairs(&self) -> Vec<(Vec<f64>, Vec<f64>)> {
+        (0..self.len())
+            .map(|i| {
+                let mut input = vec![0.0; self.input_size()];
+                let mut target = vec![0.0; self.target_size()];
+                self.write_input(i, &mut input);
+                self.write_target(i, &mut target);
+                (input, target)
+            })
+            .collect()
+    }
+}
+
+impl<'a> Subset<'a, Dataset> {
+    pub fn image(&self, index: usize) -> &'a Item<u8> {
+        self.data.image(self.indices[index])
+    }
+    pub fn label(&self, index: usize) -> u8 {
+        self.data.label(self.indices[index])
+    }
+}
+
+impl<'a, E: Examples + ?Sized> Examples for Subset<'a, E> {
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+    fn input_size(&self) -> usize {
+        self.data.input_size()
+    }
+    fn target_size(&self) -> usize {
+        self.data.target_size()
+    }
+    fn write_input(&self, index: usize, out: &mut [f64]) {
+        self.data.write_input(self.indices[index], out);
+    }
+    fn write_target(&self, index: usize, out: &mut [f64]) {
+        self.data.write_target(self.indices[index], out);
+    }
+    fn check_sizes(&self) -> Result<()> {
+        self.data.check_sizes()
+    }
+}
+
+/// Shuffles the indices `0..len` and cuts them into parts of the given sizes,
+/// which must add up to `len`. `random_split(60000, &[50000, 10000], rng)` is
+/// the usual MNIST training and validation split.
+pub fn random_split<R: Rng>(len: usize, sizes: &[usize], rng: &mut R) -> Result<Vec<Vec<usize>>> {
+    check_sizes(len, sizes)?;
+
+    let mut order: Vec<usize> = (0..len).collect();
+    rng.shuffle(&mut order);
+    let mut parts = Vec::with_capacity(sizes.len());
+    let mut start = 0;
+    for &size in sizes {
+        parts.push(order[start..start+size].to_vec());
+        start += size;
+    }
+    Ok(parts)
+}
+
+/// Like `random_split` of `labels.len()` items, but every label is shared
+/// between the parts in proportion to their sizes, to within one item.
+pub fn stratified_split<R: Rng>(labels: &[u8], sizes: &[usize], rng: &mut R) 
+                                -> Result<Vec<Vec<usize>>> 
+{
+    let len = labels.len();
+    check_sizes(len, sizes)?;
+
+    // Shuffled, then grouped by label. The sort is stable so each group stays
+    // in random order.
+    let mut order: Vec<usize> = (0..len).collect();
+    rng.shuffle(&mut order);
+    order.sort_by_key(|&i| labels[i]);
+
+    // Deal the grouped items out to whichever part is furthest behind its
+    // share so far, so that every run of them is split in proportion.
+    let mut parts: Vec<Vec<usize>> = sizes.iter().map(|&size| Vec::with_capacity(size)).collect();
+    for (position, &index) in order.iter().enumerate() {
+        let dealt = (position + 1) as f64 / len as f64;
+        let part = (0..sizes.len())
+            .filter(|&p| parts[p].len() < sizes[p])
+            .map(|p| (p, sizes[p] as f64 * dealt - parts[p].len() as f64))
+            .fold(None, |best: Option<(usize, f64)>, (p, behind)| match best {
+                Some((_, most)) if most >= behind => best,
+                _ => Some((p, behind)),
+            })
+            .map(|(p, _)| p)
+            .unwrap();
+        parts[part].push(index);
+    }
+    for part in &mut parts {
+        rng.shuffle(part);
+    }
+    Ok(parts)
+}
+
+fn check_sizes(len: usize, sizes: &[usize]) -> Result<()> {
+    let total = sizes.iter().try_fold(0usize, |total, &size| total.checked_add(size));
+    match total {
+        Some(total) if total == len => Ok(()),
+        total => Err(MnistError::SplitSizeMismatch { total: total.unwrap_or(usize::MAX), len }),
+    }
+}
+
+/// Partitions `0..len` into `k` random folds of as equal size as possible. 
+/// Fails unless there are between 2 and `len` folds.
+pub fn k_folds<R: Rng>(len: usize, k: usize, rng: &mut R) -> Result<Vec<Fold>> {
+    Ok(folds(random_split(len, &fold_sizes(len, k)?, rng)?))
+}
+
+/// Like `k_folds`, keeping the label proportions of every fold the same.
+pub fn stratified_k_folds<R: Rng>(labels: &[u8], k: usize, rng: &mut R) -> Result<Vec<Fold>> {
+    Ok(folds(stratified_split(labels, &fold_sizes(labels.len(), k)?, rng)?))
+}
+
+fn fold_sizes(len: usize, k: usize) -> Result<Vec<usize>> {
+    if k < 2 || k > len {
+        return Err(MnistError::InvalidFoldCount { folds: k, len });
+    }
+    Ok((0..k).map(|f| len / k + if f < len % k { 1 } else { 0 }).collect())
+}
+
+fn folds(parts: Vec<Vec<usize>>) -> Vec<Fold> {
+    (0..parts.len())
+        .map(|f| {
+            let train = parts.iter().enumerate()
+                .filter(|&(p, _)| p != f)
+                .flat_map(|(_, part)| part.iter().cloned())
+                .collect();
+            Fold { train, validation: parts[f].clone() }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    fn sorted(mut indices: Vec<usize>) -> Vec<usize> {
+        indices.sort();
+        indices
+    }
+
+    #[test]
+    fn random_split_partitions() {
+        let mut rng = StdRng::from_seed(&[3]);
+        let parts = random_split(100, &[70, 20, 10], &mut rng).unwrap();
+        assert_eq!(parts.iter().map(|p| p.len()).collect::<Vec<_>>(), vec![70, 20, 10]);
+        assert_eq!(sorted(parts.concat()), (0..100).collect::<Vec<_>>());
+        assert_ne!(parts[0], (0..70).collect::<Vec<_>>());
+
+        let again = random_split(100, &[70, 20, 10], &mut StdRng::from_seed(&[3])).unwrap();
+        assert_eq!(parts, again);
+
+        match random_split(100, &[70, 20], &mut rng) {
+            Err(MnistError::SplitSizeMismatch { total: 90, len: 100 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(random_split(1, &[usize::MAX, 2], &mut rng).is_err());
+    }
+
+    #[test]
+    fn stratified_split_keeps_label_proportions() {
+        // 60 zeros, 30 ones and 10 twos.
+        let labels: Vec<u8> = (0..100).map(|i| if i < 60 { 0 } else if i < 90 { 1 } else { 2 }).collect();
+        let mut rng = StdRng::from_seed(&[5]);
+        let parts = stratified_split(&labels, &[50, 30, 20], &mut rng).unwrap();
+        assert!(stratified_split(&labels, &[50, 30, 30], &mut rng).is_err());
+
+        assert_eq!(parts.iter().map(|p| p.len()).collect::<Vec<_>>(), vec![50, 30, 20]);
+        assert_eq!(sorted(parts.concat()), (0..100).collect::<Vec<_>>());
+        for (part, &size) in parts.iter().zip(&[50, 30, 20]) {
+            for &(label, count) in &[(0, 60), (1, 30), (2, 10)] {
+                let found = part.iter().filter(|&&i| labels[i] == label).count() as f64;
+                let expected = (size * count) as f64 / 100.0;
+                assert!((found - expected).abs() <= 1.0, "{} of label {}", found, label);
+            }
+        }
+    }
+
+    #[test]
+    fn folds_hold_out_each_item_once() {
+        let mut rng = StdRng::from_seed(&[8]);
+        let folds = k_folds(23, 5, &mut rng).unwrap();
+        assert_eq!(folds.iter().map(|f| f.validation.len()).collect::<Vec<_>>(),
+                   vec![5, 5, 5, 4, 4]);
+        for fold in &folds {
+            let all = sorted(fold.train.iter().chain(&fold.validation).cloned().collect());
+            assert_eq!(all, (0..23).collect::<Vec<_>>());
+        }
+        let held_out = sorted(folds.iter().flat_map(|f| f.validation.clone()).collect());
+        assert_eq!(held_out, (0..23).collect::<Vec<_>>());
+
+        let labels: Vec<u8> = (0..40).map(|i| (i % 4) as u8).collect();
+        for fold in stratified_k_folds(&labels, 4, &mut rng).unwrap() {
+            let mut counts = [0; 4];
+            for &i in &fold.validation {
+                counts[labels[i] as usize] += 1;
+            }
+            // Ten of each label over four folds.
+            assert!(counts.iter().all(|&c| c == 2 || c == 3), "{:?}", counts);
+        }
+
+        for &k in &[0, 1, 24] {
+            match k_folds(23, k, &mut rng) {
+                Err(MnistError::InvalidFoldCount { folds, len: 23 }) if folds == k => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        assert!(stratified_k_folds(&labels, 41, &mut rng).is_err());
+    }
+
+    #[test]
+    fn subsets_view_the_data() {
+        let data: Vec<(Vec<f64>, Vec<f64>)> = (0..5)
+            .map(|i| (vec![i as f64], vec![-(i as f64)]))
+            .collect();
+        let indices = [4, 1];
+        let subset = Subset::new(&data, &indices).unwrap();
+        assert_eq!(subset.len(), 2);
+        assert_eq!(subset.pairs(), vec![data[4].clone(), data[1].clone()]);
+
+        match Subset::new(&data, &[1, 5]) {
+            Err(MnistError::IndexOutOfRange { index: 5, len: 5 }) => (),
+            other => panic!("unexpected result {:?}", other.map(|s| s.len())),
+        }
+    }
+}
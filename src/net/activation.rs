@@ -0,0 +1,261 @@
+use std::fmt;
+
+use math::Vector;
+use super::cost::Canonical;
+
+pub trait Activation: fmt::Debug {
+    fn name(&self) -> &'static str;
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64>;
+    /// The elementwise derivative `da_i/dz_i`. For activations that couple
+    /// their inputs, such as softmax, this is the diagonal of the Jacobian.
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64>;
+    fn box_clone(&self) -> Box<dyn Activation>;
+
+    /// Parameters needed to reconstruct this activation with `from_name`.
+    fn params(&self) -> Vec<f64> {
+        Vec::new()
+    }
+
+    /// Maps the gradient of the cost with respect to this layer's output onto 
+    /// its weighted input, i.e. `J^T * grad`.
+    fn backprop(&self, z: &Vector<f64>, grad: &Vector<f64>) -> Vector<f64> {
+        grad.hadamard(&self.derivative(z))
+    }
+
+    /// The cost this activation forms a canonical pair with as an output 
+    /// layer, if any. Wrappers should pass this through.
+    fn canonical(&self) -> Option<Canonical> {
+        None
+    }
+}
+
+/// Looks up an activation by its `name()`, passing `params` to those that 
+/// take any.
+pub fn from_name(name: &str, params: &[f64]) -> Option<Box<dyn Activation>> {
+    let param = |default: f64| params.first().cloned().unwrap_or(default);
+    let activation: Box<dyn Activation> = match name {
+        "sigmoid" => Box::new(Sigmoid),
+        "tanh" => Box::new(Tanh),
+        "relu" => Box::new(Relu),
+        "leaky_relu" => Box::new(LeakyRelu::new(param(LeakyRelu::default().alpha))),
+        "elu" => Box::new(Elu::new(param(Elu::default().alpha))),
+        "softplus" => Box::new(Softplus),
+        "identity" => Box::new(Identity),
+        "softmax" => Box::new(Softmax),
+        _ => return None,
+    };
+    Some(activation)
+}
+
+impl Clone for Box<dyn Activation> {
+    fn clone(&self) -> Box<dyn Activation> {
+        self.box_clone()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sigmoid;
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tanh;
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Relu;
+#[derive(Clone, Copy, Debug)]
+pub struct LeakyRelu {
+    pub alpha: f64,
+}
+#[derive(Clone, Copy, Debug)]
+pub struct Elu {
+    pub alpha: f64,
+}
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Softplus;
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Identity;
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Softmax;
+
+pub fn sigmoid(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+pub fn sigmoid_prime(z: f64) -> f64 {
+    let s = sigmoid(z);
+    s * (1.0 - s)
+}
+
+impl Activation for Sigmoid {
+    fn name(&self) -> &'static str { "sigmoid" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(sigmoid)
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(sigmoid_prime)
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+    fn canonical(&self) -> Option<Canonical> { Some(Canonical::CrossEntropy) }
+}
+
+impl Activation for Tanh {
+    fn name(&self) -> &'static str { "tanh" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(f64::tanh)
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(|z| 1.0 - z.tanh().powi(2))
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+}
+
+impl Activation for Relu {
+    fn name(&self) -> &'static str { "relu" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(|z| z.max(0.0))
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(|z| if z > 0.0 { 1.0 } else { 0.0 })
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+}
+
+impl LeakyRelu {
+    pub fn new(alpha: f64) -> LeakyRelu {
+        LeakyRelu { alpha }
+    }
+}
+
+impl Default for LeakyRelu {
+    fn default() -> LeakyRelu {
+        LeakyRelu::new(0.01)
+    }
+}
+
+impl Activation for LeakyRelu {
+    fn name(&self) -> &'static str { "leaky_relu" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(|z| if z > 0.0 { z } else { self.alpha * z })
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(|z| if z > 0.0 { 1.0 } else { self.alpha })
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+    fn params(&self) -> Vec<f64> { vec![self.alpha] }
+}
+
+impl Elu {
+    pub fn new(alpha: f64) -> Elu {
+        Elu { alpha }
+    }
+}
+
+impl Default for Elu {
+    fn default() -> Elu {
+        Elu::new(1.0)
+    }
+}
+
+impl Activation for Elu {
+    fn name(&self) -> &'static str { "elu" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(|z| if z > 0.0 { z } else { self.alpha * z.exp_m1() })
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(|z| if z > 0.0 { 1.0 } else { self.alpha * z.exp() })
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+    fn params(&self) -> Vec<f64> { vec![self.alpha] }
+}
+
+impl Activation for Softplus {
+    fn name(&self) -> &'static str { "softplus" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        // ln(1 + e^z), rearranged so large |z| neither overflows nor loses precision.
+        z.map(|z| z.max(0.0) + (-z.abs()).exp().ln_1p())
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.map(sigmoid)
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+}
+
+impl Activation for Identity {
+    fn name(&self) -> &'static str { "identity" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        z.clone()
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        Vector::from_fn(z.len(), |_| 1.0)
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+}
+
+impl Activation for Softmax {
+    fn name(&self) -> &'static str { "softmax" }
+    fn apply(&self, z: &Vector<f64>) -> Vector<f64> {
+        let max = z.iter().fold(f64::NEG_INFINITY, |m, &x| m.max(x));
+        let mut exps = z.map(|z| (z - max).exp());
+        let sum = exps.sum();
+        exps.scale(1.0 / sum);
+        exps
+    }
+    fn derivative(&self, z: &Vector<f64>) -> Vector<f64> {
+        self.apply(z).map(|s| s * (1.0 - s))
+    }
+    fn backprop(&self, z: &Vector<f64>, grad: &Vector<f64>) -> Vector<f64> {
+        // J = diag(s) - s*s^T, so J^T * g = s .* (g - s.g)
+        let s = self.apply(z);
+        let dot = s.dot(grad);
+        s.hadamard(&grad.map(|g| g - dot))
+    }
+    fn box_clone(&self) -> Box<dyn Activation> { Box::new(*self) }
+    fn canonical(&self) -> Option<Canonical> { Some(Canonical::LogLikelihood) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn all() -> Vec<Box<dyn Activation>> {
+        vec![Box::new(Sigmoid), Box::new(Tanh), Box::new(Relu), 
+             Box::new(LeakyRelu::default()), Box::new(Elu::default()), 
+             Box::new(Softplus), Box::new(Identity), Box::new(Softmax)]
+    }
+
+    #[test]
+    fn backprop_matches_numeric_jacobian() {
+        let z = Vector::new(vec![-1.3, -0.2, 0.4, 2.1]);
+        let grad = Vector::new(vec![0.5, -1.0, 0.25, 2.0]);
+        let eps = 1e-6;
+
+        for act in all() {
+            let analytic = act.backprop(&z, &grad);
+            for i in 0..z.len() {
+                let mut plus = z.clone();
+                plus[i] += eps;
+                let mut minus = z.clone();
+                minus[i] -= eps;
+                let numeric = (act.apply(&plus).dot(&grad) - act.apply(&minus).dot(&grad)) 
+                    / (2.0 * eps);
+                assert!((numeric - analytic[i]).abs() < 1e-6, 
+                        "{} differs at {}: {} vs {}", act.name(), i, numeric, analytic[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn from_name_round_trips() {
+        for act in all() {
+            let copy = from_name(act.name(), &act.params()).unwrap();
+            assert_eq!(copy.name(), act.name());
+            assert_eq!(copy.params(), act.params());
+        }
+        assert_eq!(from_name("elu", &[0.5]).unwrap().params(), vec![0.5]);
+        assert!(from_name("swish", &[]).is_none());
+    }
+
+    #[test]
+    fn softmax_sums_to_one() {
+        let s = Softmax.apply(&Vector::new(vec![1000.0, 1001.0, 999.0]));
+        assert!((s.sum() - 1.0).abs() < 1e-12);
+        assert_eq!(s.argmax(), 1);
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+
+use math::Vector;
+use super::activation::Activation;
+
+/// Keeps logarithms finite when an output saturates at exactly 0 or 1.
+const LOG_EPSILON: f64 = 1e-12;
+
+pub trait Cost: fmt::Debug {
+    fn name(&self) -> &'static str;
+    fn cost(&self, output: &Vector<f64>, target: &Vector<f64>) -> f64;
+    /// The gradient `dC/da` of the cost with respect to the network output.
+    fn gradient(&self, output: &Vector<f64>, target: &Vector<f64>) -> Vector<f64>;
+    fn box_clone(&self) -> Box<dyn Cost>;
+
+    /// Which canonical pairing this cost takes part in, if any.
+    fn canonical(&self) -> Option<Canonical> {
+        None
+    }
+
+    /// The output layer error `dC/dz`, given the weighted input `z` and 
+    /// `output = activation(z)`. When the cost and activation form a 
+    /// canonical pair this is just `a - y`.
+    fn delta(&self, z: &Vector<f64>, output: &Vector<f64>, target: &Vector<f64>, 
+             activation: &dyn Activation) -> Vector<f64> 
+    {
+        match self.canonical() {
+            Some(pair) if activation.canonical() == Some(pair) => {
+                let mut delta = output.clone();
+                delta.sub_assign(target);
+                delta
+            },
+            _ => activation.backprop(z, &self.gradient(output, target)),
+        }
+    }
+}
+
+/// Cost and output activation pairs whose derivatives cancel, leaving `a - y` 
+/// as the output error: cross-entropy with sigmoid and log-likelihood with 
+/// softmax.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Canonical {
+    CrossEntropy,
+    LogLikelihood,
+}
+
+/// Looks up a cost function by its `name()`.
+pub fn from_name(name: &str) -> Option<Box<dyn Cost>> {
+    match name {
+        "quadratic" => Some(Box::new(Quadratic)),
+        "cross_entropy" => Some(Box::new(CrossEntropy)),
+        "log_likelihood" => Some(Box::new(LogLikelihood)),
+        _ => None,
+    }
+}
+
+impl Clone for Box<dyn Cost> {
+    fn clone(&self) -> Box<dyn Cost> {
+        self.box_clone()
+    }
+}
+
+/// Mean squared error, `0.5 * ||a - y||^2`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Quadratic;
+
+/// Binary cross-entropy summed over the outputs. With a sigmoid output layer 
+/// the `sigma'(z)` term cancels, avoiding learning slowdown on saturated neurons.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrossEntropy;
+
+/// Categorical negative log-likelihood, `-ln a_y`, intended for a softmax 
+/// output layer and one-hot targets.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogLikelihood;
+
+impl Cost for Quadratic {
+    fn name(&self) -> &'static str { "quadratic" }
+    fn cost(&self, output: &Vector<f64>, target: &Vector<f64>) -> f64 {
+        let mut diff = output.clone();
+        diff.sub_assign(target);
+        0.5 * diff.dot(&diff)
+    }
+    fn gradient(&self, output: &Vector<f64>, target: &Vector<f64>) -> Vector<f64> {
+        let mut diff = output.clone();
+        diff.sub_assign(target);
+        diff
+    }
+    fn box_clone(&self) -> Box<dyn Cost> { Box::new(*self) }
+}
+
+impl Cost for CrossEntropy {
+    fn name(&self) -> &'static str { "cross_entropy" }
+    fn cost(&self, output: &Vector<f64>, target: &Vector<f64>) -> f64 {
+        output.iter().zip(target.iter())
+            .map(|(&a, &y)| {
+                let a = a.clamp(LOG_EPSILON, 1.0 - LOG_EPSILON);
+                -(y * a.ln() + (1.0 - y) * (1.0 - a).ln())
+            })
+            .sum()
+    }
+    fn gradient(&self, output: &Vector<f64>, target: &Vector<f64>) -> Vector<f64> {
+        Vector::from_fn(output.len(), |i| {
+            let a = output[i].clamp(LOG_EPSILON, 1.0 - LOG_EPSILON);
+            (a - target[i]) / (a * (1.0 - a))
+        })
+    }
+    fn box_clone(&self) -> Box<dyn Cost> { Box::new(*self) }
+    fn canonical(&self) -> Option<Canonical> { Some(Canonical::CrossEntropy) }
+}
+
+impl Cost for LogLikelihood {
+    fn name(&self) -> &'static str { "log_likelihood" }
+    fn cost(&self, output: &Vector<f64>, target: &Vector<f64>) -> f64 {
+        output.iter().zip(target.iter())
+            .map(|(&a, &y)| -y * a.max(LOG_EPSILON).ln())
+            .sum()
+    }
+    fn gradient(&self, output: &Vector<f64>, target: &Vector<f64>) -> Vector<f64> {
+        Vector::from_fn(output.len(), |i| -target[i] / output[i].max(LOG_EPSILON))
+    }
+    fn box_clone(&self) -> Box<dyn Cost> { Box::new(*self) }
+    fn canonical(&self) -> Option<Canonical> { Some(Canonical::LogLikelihood) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use net::activation::{Sigmoid, Softmax};
+
+    fn numeric_delta(cost: &dyn Cost, act: &dyn Activation, 
+                     z: &Vector<f64>, y: &Vector<f64>) -> Vector<f64> 
+    {
+        let eps = 1e-6;
+        Vector::from_fn(z.len(), |i| {
+            let mut plus = z.clone();
+            plus[i] += eps;
+            let mut minus = z.clone();
+            minus[i] -= eps;
+            (cost.cost(&act.apply(&plus), y) - cost.cost(&act.apply(&minus), y)) / (2.0 * eps)
+        })
+    }
+
+    #[test]
+    fn output_deltas_match_numeric() {
+        let z = Vector::new(vec![0.3, -1.2, 2.0]);
+        let y = Vector::new(vec![0.0, 1.0, 0.0]);
+        let pairs: Vec<(Box<dyn Cost>, Box<dyn Activation>)> = vec![
+            (Box::new(Quadratic), Box::new(Sigmoid)),
+            (Box::new(Quadratic), Box::new(Softmax)),
+            (Box::new(CrossEntropy), Box::new(Sigmoid)),
+            (Box::new(LogLikelihood), Box::new(Softmax)),
+        ];
+
+        for (cost, act) in pairs {
+            let a = act.apply(&z);
+            let delta = cost.delta(&z, &a, &y, &*act);
+            let numeric = numeric_delta(&*cost, &*act, &z, &y);
+            for i in 0..z.len() {
+                assert!((delta[i] - numeric[i]).abs() < 1e-6, 
+                        "{}/{} differs at {}", cost.name(), act.name(), i);
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_pairs_take_the_closed_form() {
+        let z = Vector::new(vec![0.3, -1.2, 2.0]);
+        let y = Vector::new(vec![0.0, 1.0, 0.0]);
+        let pairs: Vec<(Box<dyn Cost>, Box<dyn Activation>)> = vec![
+            (Box::new(CrossEntropy), Box::new(Sigmoid)),
+            (Box::new(LogLikelihood), Box::new(Softmax)),
+        ];
+
+        for (cost, act) in pairs {
+            assert_eq!(cost.canonical(), act.canonical());
+            let a = act.apply(&z);
+            let fast = cost.delta(&z, &a, &y, &*act);
+            let general = act.backprop(&z, &cost.gradient(&a, &y));
+            for i in 0..z.len() {
+                assert!((fast[i] - (a[i] - y[i])).abs() < 1e-12);
+                assert!((fast[i] - general[i]).abs() < 1e-9, 
+                        "{}/{} differs at {}", cost.name(), act.name(), i);
+            }
+        }
+        assert_ne!(CrossEntropy.canonical(), Softmax.canonical());
+        assert_eq!(Quadratic.canonical(), None);
+    }
+
+    #[test]
+    fn cost_values() {
+        let a = Vector::new(vec![0.5, 0.25, 0.25]);
+        let y = Vector::new(vec![1.0, 0.0, 0.0]);
+
+        assert!((Quadratic.cost(&a, &y) - 0.1875).abs() < 1e-12);
+        assert!((LogLikelihood.cost(&a, &y) - 2f64.ln()).abs() < 1e-12);
+        let expected = -(0.5f64.ln() + 2.0 * 0.75f64.ln());
+        assert!((CrossEntropy.cost(&a, &y) - expected).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,121 @@
+use rand::Rng;
+
+use mnist::batch::Examples;
+use mnist::error::{MnistError, Result};
+use mnist::split::{Fold, Subset};
+use super::network::Network;
+use super::train::{History, Sgd};
+
+/// How the network trained on one fold did on the held-out examples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoldResult {
+    pub history: History,
+    pub accuracy: f64,
+    pub cost: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrossValidation {
+    pub folds: Vec<FoldResult>,
+}
+
+impl CrossValidation {
+    pub fn mean_accuracy(&self) -> f64 {
+        mean(self.folds.iter().map(|f| f.accuracy))
+    }
+    /// The sample standard deviation of the fold accuracies.
+    pub fn accuracy_std_dev(&self) -> f64 {
+        let n = self.folds.len() as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let mean = self.mean_accuracy();
+        let sum_sq: f64 = self.folds.iter().map(|f| (f.accuracy - mean).powi(2)).sum();
+        (sum_sq / (n - 1.0)).sqrt()
+    }
+    pub fn mean_cost(&self) -> f64 {
+        mean(self.folds.iter().map(|f| f.cost))
+    }
+}
+
+fn mean<I: Iterator<Item=f64>>(values: I) -> f64 {
+    let (sum, n) = values.fold((0.0, 0), |(sum, n), x| (sum + x, n + 1));
+    if n == 0 { 0.0 } else { sum / n as f64 }
+}
+
+/// Trains a fresh network from `build` on each fold with a copy of `sgd` and
+/// scores it on the held-out examples. The folds are views into `data`, so
+/// no examples are copied up front. The held-out fold is also what
+/// `Sgd::fit` validates on, so it drives early stopping and plateau schedules
+/// when those are enabled. Fails if a fold refers to an example `data` 
+/// doesn't have, or has no examples to train or validate on.
+pub fn cross_validate<E, R, F>(sgd: &Sgd, data: &E, folds: &[Fold], rng: &mut R,
+                               mut build: F) -> Result<CrossValidation>
+    where E: Examples + ?Sized, R: Rng, F: FnMut(&mut R) -> Network
+{
+    let mut results = Vec::with_capacity(folds.len());
+    for (index, fold) in folds.iter().enumerate() {
+        let training = Subset::new(data, &fold.train)?;
+        let validation = Subset::new(data, &fold.validation)?;
+        if training.is_empty() || validation.is_empty() {
+            return Err(MnistError::EmptyFold(index));
+        }
+
+        let mut net = build(rng);
+        let mut sgd = sgd.clone();
+        let history = sgd.fit(&mut net, &training, &validation, rng, |_, _| ());
+        results.push(FoldResult {
+            history,
+            accuracy: net.evaluate(&validation) as f64 / validation.len() as f64,
+            cost: sgd.total_cost(&net, &validation, training.len()),
+        });
+    }
+    Ok(CrossValidation { folds: results })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use mnist::split::{k_folds, Fold};
+    use net::geom::Geometry;
+
+    #[test]
+    fn trains_one_network_per_fold() {
+        // Two separable classes.
+        let data: Vec<(Vec<f64>, Vec<f64>)> = (0..40)
+            .map(|i| {
+                let x = i as f64 / 40.0;
+                let target = if x < 0.5 { vec![1.0, 0.0] } else { vec![0.0, 1.0] };
+                (vec![x, 1.0 - x], target)
+            })
+            .collect();
+        let mut rng = StdRng::from_seed(&[2]);
+        let folds = k_folds(data.len(), 4, &mut rng).unwrap();
+        let sgd = Sgd::new(30, 4, 3.0);
+
+        let mut built = 0;
+        let cv = cross_validate(&sgd, &data[..], &folds, &mut rng, |rng| {
+            built += 1;
+            Network::with_rng(Geometry::new(vec![2, 4, 2]), rng)
+        }).unwrap();
+
+        assert_eq!(built, 4);
+        assert_eq!(cv.folds.len(), 4);
+        assert!(cv.folds.iter().all(|f| f.history.epochs.len() == 30));
+        assert!(cv.mean_accuracy() > 0.8, "{}", cv.mean_accuracy());
+        assert!(cv.accuracy_std_dev() >= 0.0 && cv.mean_cost() > 0.0);
+
+        let build = |rng: &mut StdRng| Network::with_rng(Geometry::new(vec![2, 4, 2]), rng);
+        let bad = [Fold { train: vec![0, 1], validation: vec![40] }];
+        assert!(cross_validate(&sgd, &data, &bad, &mut rng, build).is_err());
+        for empty in &[Fold { train: vec![0, 1], validation: vec![] },
+                       Fold { train: vec![], validation: vec![0, 1] }] {
+            let folds = [folds[0].clone(), empty.clone()];
+            match cross_validate(&sgd, &data, &folds, &mut rng, build) {
+                Err(MnistError::EmptyFold(1)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+}
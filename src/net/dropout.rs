@@ -0,0 +1,82 @@
+use rand::Rng;
+
+use math::Vector;
+use super::network::Network;
+
+/// Per hidden layer dropout probabilities, applied only while training.
+///
+/// Uses inverted dropout: kept activations are scaled by `1/(1-p)` during 
+/// training so the network needs no rescaling at inference time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dropout {
+    rates: Vec<f64>,
+}
+
+/// A sampled dropout mask for each weight layer's output, holding `0` for 
+/// dropped neurons and `1/(1-p)` for kept ones. `None` leaves a layer alone.
+pub type Masks = Vec<Option<Vector<f64>>>;
+
+impl Dropout {
+    /// `rates[i]` is the probability of dropping each neuron of hidden layer `i`.
+    pub fn new(rates: Vec<f64>) -> Dropout {
+        assert!(rates.iter().all(|&p| (0.0..1.0).contains(&p)), 
+                "Dropout rates must be in [0, 1)");
+        Dropout { rates }
+    }
+    pub fn none() -> Dropout {
+        Dropout::default()
+    }
+    /// The same rate for each of `num_hidden` hidden layers.
+    pub fn uniform(num_hidden: usize, rate: f64) -> Dropout {
+        Dropout::new(vec![rate; num_hidden])
+    }
+
+    pub fn rate(&self, hidden_layer: usize) -> f64 {
+        self.rates.get(hidden_layer).cloned().unwrap_or(0.0)
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.rates.iter().any(|&p| p > 0.0)
+    }
+
+    /// Draws fresh masks for one training example.
+    pub fn sample_masks<R: Rng>(&self, net: &Network, rng: &mut R) -> Masks {
+        let layers = net.geometry().layers();
+        (0..layers.len()-1)
+            .map(|layer| {
+                let is_hidden = layer < layers.len()-2;
+                let p = self.rate(layer);
+                if !is_hidden || p == 0.0 {
+                    return None;
+                }
+                let keep = 1.0 / (1.0 - p);
+                Some(Vector::from_fn(layers[layer+1], |_| {
+                    if rng.next_f64() < p { 0.0 } else { keep }
+                }))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use net::geom::Geometry;
+
+    #[test]
+    fn masks() {
+        let net = Network::new(Geometry::new(vec![2, 1000, 3, 1]));
+        let dropout = Dropout::new(vec![0.5]);
+
+        let masks = dropout.sample_masks(&net, &mut StdRng::from_seed(&[1]));
+        assert_eq!(masks.len(), 3);
+        assert!(masks[1].is_none() && masks[2].is_none());
+
+        let mask = masks[0].as_ref().unwrap();
+        assert!(mask.iter().all(|&m| m == 0.0 || m == 2.0));
+        assert!((mask.sum() / 1000.0 - 1.0).abs() < 0.1);
+
+        let again = dropout.sample_masks(&net, &mut StdRng::from_seed(&[1]));
+        assert_eq!(masks, again);
+    }
+}
@@ -0,0 +1,81 @@
+use std::io;
+use std::fmt;
+use std::result;
+use std::error::Error;
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion { found: u16, supported: u16 },
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The payload has this many bytes left over after everything it should hold.
+    TrailingData(usize),
+    /// The parameters stored for `layer` do not fit the network geometry.
+    ShapeMismatch { layer: usize, expected: (usize, usize), found: (usize, usize) },
+    InvalidGeometry(Vec<usize>),
+    LayerCountMismatch { expected: usize, found: usize },
+    UnknownActivation(String),
+    UnknownOptimizer(String),
+    NonFiniteValue,
+    /// The network has `found` outputs where `expected` classes were scored.
+    OutputSizeMismatch { expected: usize, found: usize },
+    InvalidClass { label: usize, num_classes: usize },
+    Json { offset: usize, message: String },
+}
+
+pub type Result<T> = result::Result<T, NetError>;
+
+impl From<io::Error> for NetError {
+    fn from(error: io::Error) -> NetError {
+        NetError::Io(error)
+    }
+}
+
+impl Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetError::Io(ref err) => write!(f, "{}", err),
+            NetError::InvalidMagic => 
+                write!(f, "Not a network file"),
+            NetError::UnsupportedVersion { found, supported } =>
+                write!(f, "Network file version {} is not supported (newest is {})", 
+                       found, supported),
+            NetError::ChecksumMismatch { expected, found } =>
+                write!(f, "Checksum mismatch: file says 0x{:08x}, contents hash to 0x{:08x}", 
+                       expected, found),
+            NetError::TrailingData(len) =>
+                write!(f, "Network file has {} bytes of unexpected data after the parameters", len),
+            NetError::ShapeMismatch { layer, expected, found } =>
+                write!(f, "Layer {} has shape {}x{} but the geometry requires {}x{}", 
+                       layer, found.0, found.1, expected.0, expected.1),
+            NetError::InvalidGeometry(ref layers) =>
+                write!(f, "Invalid geometry {:?}: a network needs at least two non-empty layers", 
+                       layers),
+            NetError::LayerCountMismatch { expected, found } =>
+                write!(f, "Expected parameters for {} layers, found {}", expected, found),
+            NetError::UnknownActivation(ref name) =>
+                write!(f, "Unknown activation function '{}'", name),
+            NetError::UnknownOptimizer(ref name) =>
+                write!(f, "Unknown optimizer '{}'", name),
+            NetError::NonFiniteValue =>
+                write!(f, "Network contains NaN or infinite values"),
+            NetError::OutputSizeMismatch { expected, found } =>
+                write!(f, "Network has {} outputs but {} classes are expected", found, expected),
+            NetError::InvalidClass { label, num_classes } =>
+                write!(f, "Label {} is out of range for {} classes", label, num_classes),
+            NetError::Json { offset, ref message } =>
+                write!(f, "Invalid JSON at byte {}: {}", offset, message),
+        }
+    }
+}
+
+impl Error for NetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            NetError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
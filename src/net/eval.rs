@@ -0,0 +1,182 @@
+use std::fmt;
+
+use mnist::Dataset;
+use mnist::dataset::{normalize, NUM_CLASSES};
+use super::error::{NetError, Result};
+use super::network::Network;
+
+/// Classification metrics accumulated over a labeled dataset.
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    /// `confusion[actual][predicted]`
+    confusion: Vec<Vec<usize>>,
+    top_k: usize,
+    top_k_correct: usize,
+    total: usize,
+}
+
+impl Evaluation {
+    pub fn new(num_classes: usize, top_k: usize) -> Evaluation {
+        assert!(top_k > 0);
+        Evaluation {
+            confusion: vec![vec![0; num_classes]; num_classes],
+            top_k,
+            top_k_correct: 0,
+            total: 0,
+        }
+    }
+
+    /// Runs `net` over every `(input, label)` pair.
+    pub fn run<'a, I>(net: &Network, data: I, num_classes: usize, 
+                      top_k: usize) -> Result<Evaluation>
+        where I: IntoIterator<Item=(&'a [f64], usize)>
+    {
+        let mut eval = Evaluation::new(num_classes, top_k);
+        for (input, label) in data {
+            eval.record(&net.feed_forward(input), label)?;
+        }
+        Ok(eval)
+    }
+
+    pub fn from_dataset(net: &Network, data: &Dataset, top_k: usize) -> Result<Evaluation> {
+        let mut eval = Evaluation::new(NUM_CLASSES, top_k);
+        for (image, &label) in data.images().iter().zip(data.labels().iter()) {
+            eval.record(&net.feed_forward(&normalize(image)), label as usize)?;
+        }
+        Ok(eval)
+    }
+
+    /// Scores one network output, which must have one value per class.
+    pub fn record(&mut self, output: &[f64], label: usize) -> Result<()> {
+        if output.len() != self.num_classes() {
+            return Err(NetError::OutputSizeMismatch { 
+                expected: self.num_classes(), 
+                found: output.len(),
+            });
+        }
+        if label >= self.num_classes() {
+            return Err(NetError::InvalidClass { label, num_classes: self.num_classes() });
+        }
+        let mut ranked: Vec<usize> = (0..output.len()).collect();
+        ranked.sort_by(|&a, &b| output[b].partial_cmp(&output[a]).unwrap_or(::std::cmp::Ordering::Equal));
+
+        self.confusion[label][ranked[0]] += 1;
+        if ranked.iter().take(self.top_k).any(|&c| c == label) {
+            self.top_k_correct += 1;
+        }
+        self.total += 1;
+        Ok(())
+    }
+
+    pub fn num_classes(&self) -> usize {
+        self.confusion.len()
+    }
+    pub fn total(&self) -> usize {
+        self.total
+    }
+    pub fn correct(&self) -> usize {
+        (0..self.num_classes()).map(|c| self.confusion[c][c]).sum()
+    }
+    pub fn confusion_matrix(&self) -> &[Vec<usize>] {
+        &self.confusion
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        ratio(self.correct(), self.total)
+    }
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+    pub fn top_k_accuracy(&self) -> f64 {
+        ratio(self.top_k_correct, self.total)
+    }
+
+    /// Of the items predicted as `class`, the fraction that really are.
+    pub fn precision(&self, class: usize) -> f64 {
+        let predicted = self.confusion.iter().map(|row| row[class]).sum();
+        ratio(self.confusion[class][class], predicted)
+    }
+    /// Of the items that really are `class`, the fraction predicted as such.
+    pub fn recall(&self, class: usize) -> f64 {
+        let actual = self.confusion[class].iter().sum();
+        ratio(self.confusion[class][class], actual)
+    }
+    pub fn f1(&self, class: usize) -> f64 {
+        let (p, r) = (self.precision(class), self.recall(class));
+        if p + r == 0.0 { 0.0 } else { 2.0 * p * r / (p + r) }
+    }
+}
+
+fn ratio(num: usize, denom: usize) -> f64 {
+    if denom == 0 { 0.0 } else { num as f64 / denom as f64 }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Accuracy: {:.2}% ({} / {})", 
+                 100.0 * self.accuracy(), self.correct(), self.total)?;
+        writeln!(f, "Top-{} accuracy: {:.2}%", self.top_k, 100.0 * self.top_k_accuracy())?;
+
+        writeln!(f, "Confusion matrix (rows: actual, columns: predicted):")?;
+        write!(f, "     ")?;
+        for c in 0..self.num_classes() {
+            write!(f, "{:>6}", c)?;
+        }
+        writeln!(f)?;
+        for (actual, row) in self.confusion.iter().enumerate() {
+            write!(f, "{:>5}", actual)?;
+            for count in row {
+                write!(f, "{:>6}", count)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Class  Precision  Recall      F1")?;
+        for c in 0..self.num_classes() {
+            write!(f, "{:>5}  {:>9.4}  {:>6.4}  {:>6.4}", 
+                   c, self.precision(c), self.recall(c), self.f1(c))?;
+            if c < self.num_classes()-1 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn metrics() {
+        let mut eval = Evaluation::new(3, 2);
+        eval.record(&[0.9, 0.05, 0.05], 0).unwrap();
+        eval.record(&[0.6, 0.3, 0.1], 1).unwrap();
+        eval.record(&[0.1, 0.8, 0.1], 1).unwrap();
+        eval.record(&[0.2, 0.1, 0.7], 2).unwrap();
+
+        assert_eq!(eval.correct(), 3);
+        assert_eq!(eval.accuracy(), 0.75);
+        assert_eq!(eval.top_k_accuracy(), 1.0);
+        assert_eq!(eval.confusion_matrix()[1], vec![1, 1, 0]);
+        assert_eq!(eval.precision(0), 0.5);
+        assert_eq!(eval.recall(0), 1.0);
+        assert_eq!(eval.recall(1), 0.5);
+        assert!((eval.f1(0) - 2.0 / 3.0).abs() < 1e-12);
+        assert!(eval.to_string().starts_with("Accuracy: 75.00% (3 / 4)\nTop-2 accuracy: 100.00%"));
+    }
+
+    #[test]
+    fn rejects_mismatched_outputs_and_labels() {
+        let mut eval = Evaluation::new(3, 1);
+        match eval.record(&[0.1, 0.2, 0.3, 0.4], 0) {
+            Err(NetError::OutputSizeMismatch { expected: 3, found: 4 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        match eval.record(&[0.1, 0.2, 0.3], 3) {
+            Err(NetError::InvalidClass { label: 3, num_classes: 3 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(eval.total(), 0);
+    }
+}
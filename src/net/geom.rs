@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Clone, Debug)]
 pub struct Geometry {
@@ -8,14 +9,57 @@ pub struct Geometry {
 
 impl Geometry {
     pub fn new(layers: Vec<usize>) -> Geometry {
-        let num_neurons = 
-            layers.iter().fold(0, |sum, &x| sum + x);
+        let num_neurons = layers.iter().sum();
 
         Geometry {
             layers_geometry: layers,
-            num_neurons: num_neurons,
+            num_neurons,
         }
     }
+
+    pub fn layers(&self) -> &[usize] {
+        &self.layers_geometry
+    }
+    pub fn num_layers(&self) -> usize {
+        self.layers_geometry.len()
+    }
+    pub fn num_neurons(&self) -> usize {
+        self.num_neurons
+    }
+    pub fn input_size(&self) -> usize {
+        self.layers_geometry[0]
+    }
+    pub fn output_size(&self) -> usize {
+        self.layers_geometry[self.layers_geometry.len()-1]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseGeometryError(String);
+
+impl FromStr for Geometry {
+    type Err = ParseGeometryError;
+
+    /// Parses comma separated layer sizes such as `784,30,10`.
+    fn from_str(s: &str) -> Result<Geometry, ParseGeometryError> {
+        let layers = s.split(',')
+            .map(|size| match size.trim().parse::<usize>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(ParseGeometryError(format!("invalid layer size '{}'", size.trim()))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if layers.len() < 2 {
+            return Err(ParseGeometryError("at least two layers are required".to_string()));
+        }
+        Ok(Geometry::new(layers))
+    }
+}
+
+impl fmt::Display for ParseGeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
 impl fmt::Display for Geometry {
@@ -31,3 +75,19 @@ impl fmt::Display for Geometry {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let geometry: Geometry = "784, 30,10".parse().unwrap();
+        assert_eq!(geometry.layers(), &[784, 30, 10]);
+        assert_eq!(geometry.to_string(), "[784 -> 30 -> 10]");
+
+        assert!("784".parse::<Geometry>().is_err());
+        assert!("784,0,10".parse::<Geometry>().is_err());
+        assert!("784,x".parse::<Geometry>().is_err());
+    }
+}
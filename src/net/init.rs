@@ -0,0 +1,204 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use rand::distributions::{Normal, IndependentSample};
+
+use math::{Matrix, Vector};
+
+/// How the weights and biases of one layer are drawn. `fan_in` and `fan_out`
+/// are the sizes of the layers a weight matrix connects.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Initializer {
+    /// Weights and biases from `N(0, 1)`.
+    #[default]
+    StandardNormal,
+    /// Weights from `N(0, 1/fan_in)`, biases from `N(0, 1)`.
+    ScaledNormal,
+    /// Weights from `U(-a, a)` with `a = sqrt(6 / (fan_in + fan_out))`, zero biases.
+    XavierUniform,
+    /// Weights from `N(0, 2 / (fan_in + fan_out))`, zero biases.
+    XavierNormal,
+    /// Weights from `N(0, 2/fan_in)`, zero biases.
+    HeNormal,
+    /// Weights from `U(-a, a)` with `a = sqrt(6/fan_in)`, zero biases.
+    HeUniform,
+    /// A random (semi-)orthogonal weight matrix times `gain`, zero biases.
+    Orthogonal { gain: f64 },
+    /// Every weight and bias set to the same value.
+    Constant(f64),
+}
+
+impl Initializer {
+    /// A `fan_out x fan_in` weight matrix.
+    pub fn weights<R: Rng>(&self, fan_out: usize, fan_in: usize, rng: &mut R) -> Matrix<f64> {
+        let (n_in, n_out) = (fan_in as f64, fan_out as f64);
+        let normal = |std_dev: f64, rng: &mut R| {
+            let dist = Normal::new(0.0, std_dev);
+            Matrix::from_fn(fan_out, fan_in, |_, _| dist.ind_sample(rng))
+        };
+        let uniform = |limit: f64, rng: &mut R| {
+            Matrix::from_fn(fan_out, fan_in, |_, _| rng.gen_range(-limit, limit))
+        };
+
+        match *self {
+            Initializer::StandardNormal => normal(1.0, rng),
+            Initializer::ScaledNormal => normal(1.0 / n_in.sqrt(), rng),
+            Initializer::XavierUniform => uniform((6.0 / (n_in + n_out)).sqrt(), rng),
+            Initializer::XavierNormal => normal((2.0 / (n_in + n_out)).sqrt(), rng),
+            Initializer::HeNormal => normal((2.0 / n_in).sqrt(), rng),
+            Initializer::HeUniform => uniform((6.0 / n_in).sqrt(), rng),
+            Initializer::Orthogonal { gain } => {
+                let mut w = orthogonal(fan_out, fan_in, rng);
+                w.scale(gain);
+                w
+            },
+            Initializer::Constant(c) => Matrix::from_fn(fan_out, fan_in, |_, _| c),
+        }
+    }
+
+    pub fn biases<R: Rng>(&self, len: usize, rng: &mut R) -> Vector<f64> {
+        match *self {
+            Initializer::StandardNormal | Initializer::ScaledNormal => {
+                let normal = Normal::new(0.0, 1.0);
+                Vector::from_fn(len, |_| normal.ind_sample(rng))
+            },
+            Initializer::Constant(c) => Vector::from_fn(len, |_| c),
+            _ => Vector::zeros(len),
+        }
+    }
+}
+
+/// A matrix with orthonormal rows or columns, whichever there are fewer of,
+/// from Gram-Schmidt on a Gaussian matrix.
+fn orthogonal<R: Rng>(rows: usize, cols: usize, rng: &mut R) -> Matrix<f64> {
+    if rows > cols {
+        return orthogonal(cols, rows, rng).transpose();
+    }
+    let normal = Normal::new(0.0, 1.0);
+    let mut m = Matrix::from_fn(rows, cols, |_, _| normal.ind_sample(rng));
+    for i in 0..rows {
+        for j in 0..i {
+            let dot: f64 = m.row(i).iter().zip(m.row(j)).map(|(a, b)| a * b).sum();
+            let prev = m.row(j).to_vec();
+            for (x, p) in m.row_mut(i).iter_mut().zip(prev) {
+                *x -= dot * p;
+            }
+        }
+        let norm = m.row(i).iter().map(|x| x * x).sum::<f64>().sqrt();
+        for x in m.row_mut(i) {
+            *x /= norm;
+        }
+    }
+    m
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseInitializerError(String);
+
+impl FromStr for Initializer {
+    type Err = ParseInitializerError;
+
+    /// Parses `normal`, `scaled-normal`, `xavier-uniform`, `xavier-normal`,
+    /// `he-normal`, `he-uniform`, `orthogonal[:GAIN]` or `constant:VALUE`.
+    fn from_str(s: &str) -> Result<Initializer, ParseInitializerError> {
+        let (name, arg) = match s.find(':') {
+            Some(i) => (&s[..i], Some(&s[i+1..])),
+            None => (s, None),
+        };
+        let number = |default: Option<f64>| match arg {
+            Some(a) => a.parse::<f64>().ok().filter(|x| x.is_finite())
+                .ok_or_else(|| ParseInitializerError(format!("invalid argument '{}'", a))),
+            None => default
+                .ok_or_else(|| ParseInitializerError(format!("'{}' needs an argument", name))),
+        };
+
+        let init = match name {
+            "normal" => Initializer::StandardNormal,
+            "scaled-normal" => Initializer::ScaledNormal,
+            "xavier-uniform" => Initializer::XavierUniform,
+            "xavier-normal" => Initializer::XavierNormal,
+            "he-normal" => Initializer::HeNormal,
+            "he-uniform" => Initializer::HeUniform,
+            "orthogonal" => return Ok(Initializer::Orthogonal { gain: number(Some(1.0))? }),
+            "constant" => return Ok(Initializer::Constant(number(None)?)),
+            _ => return Err(ParseInitializerError(format!("unknown initializer '{}'", name))),
+        };
+        match arg {
+            Some(_) => Err(ParseInitializerError(format!("'{}' takes no argument", name))),
+            None => Ok(init),
+        }
+    }
+}
+
+impl fmt::Display for ParseInitializerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    fn variance(m: &Matrix<f64>) -> f64 {
+        let n = m.as_slice().len() as f64;
+        let mean = m.as_slice().iter().sum::<f64>() / n;
+        m.as_slice().iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n
+    }
+
+    #[test]
+    fn scales() {
+        let mut rng = StdRng::from_seed(&[4]);
+        let (fan_out, fan_in) = (100, 400);
+        let cases = [
+            (Initializer::ScaledNormal, 1.0 / 400.0),
+            (Initializer::XavierUniform, 2.0 / 500.0),
+            (Initializer::XavierNormal, 2.0 / 500.0),
+            (Initializer::HeNormal, 2.0 / 400.0),
+            (Initializer::HeUniform, 2.0 / 400.0),
+        ];
+        for &(init, expected) in &cases {
+            let v = variance(&init.weights(fan_out, fan_in, &mut rng));
+            assert!((v / expected - 1.0).abs() < 0.05, "{:?}: {}", init, v);
+        }
+
+        let limit = (6.0f64 / 500.0).sqrt();
+        let w = Initializer::XavierUniform.weights(fan_out, fan_in, &mut rng);
+        assert!(w.as_slice().iter().all(|x| x.abs() <= limit));
+        assert_eq!(Initializer::HeNormal.biases(3, &mut rng), Vector::zeros(3));
+    }
+
+    #[test]
+    fn orthogonal_rows_and_columns() {
+        let mut rng = StdRng::from_seed(&[9]);
+        for &(rows, cols) in &[(4, 7), (7, 4), (5, 5)] {
+            let w = Initializer::Orthogonal { gain: 2.0 }.weights(rows, cols, &mut rng);
+            // W W^T or W^T W is 4 I.
+            let gram = if rows <= cols { w.mul(&w.transpose()) } else { w.transpose().mul(&w) };
+            for i in 0..gram.rows() {
+                for j in 0..gram.cols() {
+                    let expected = if i == j { 4.0 } else { 0.0 };
+                    assert!((gram[(i, j)] - expected).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seeded() {
+        let draw = || Initializer::HeUniform.weights(3, 3, &mut StdRng::from_seed(&[1]));
+        assert_eq!(draw(), draw());
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("he-normal".parse(), Ok(Initializer::HeNormal));
+        assert_eq!("orthogonal".parse(), Ok(Initializer::Orthogonal { gain: 1.0 }));
+        assert_eq!("constant:0.5".parse(), Ok(Initializer::Constant(0.5)));
+        assert!("constant".parse::<Initializer>().is_err());
+        assert!("xavier-normal:2".parse::<Initializer>().is_err());
+        assert!("lecun".parse::<Initializer>().is_err());
+    }
+}
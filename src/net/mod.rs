@@ -1 +1,18 @@
 pub mod geom;
+pub mod error;
+pub mod activation;
+pub mod cost;
+pub mod regularization;
+pub mod dropout;
+pub mod init;
+pub mod network;
+pub mod optimizer;
+pub mod schedule;
+pub mod train;
+pub mod cross_validation;
+pub mod eval;
+pub mod json;
+pub mod serialize;
+
+pub use self::network::Network;
+pub use self::train::Sgd;
@@ -0,0 +1,258 @@
+use rand;
+use rand::Rng;
+
+use math::{Matrix, Vector};
+use mnist::batch::{Batches, Examples};
+use super::activation::{Activation, Sigmoid};
+use super::error::{NetError, Result};
+use super::geom::Geometry;
+use super::init::Initializer;
+
+const EVALUATION_BATCH_SIZE: usize = 100;
+
+#[derive(Clone, Debug)]
+pub struct Network {
+    geometry: Geometry,
+    weights: Vec<Matrix<f64>>,
+    biases: Vec<Vector<f64>>,
+    activations: Vec<Box<dyn Activation>>,
+}
+
+impl Network {
+    pub fn new(geometry: Geometry) -> Network {
+        Network::with_rng(geometry, &mut rand::thread_rng())
+    }
+
+    pub fn with_rng<R: Rng>(geometry: Geometry, rng: &mut R) -> Network {
+        assert!(geometry.num_layers() > 1, 
+                "A network needs at least an input and an output layer");
+
+        let num_weight_layers = geometry.num_layers()-1;
+        let activations = (0..num_weight_layers)
+            .map(|_| Box::new(Sigmoid) as Box<dyn Activation>)
+            .collect();
+        Network::with_activations(geometry, activations, rng)
+    }
+
+    /// Builds a network with one activation per non-input layer of `geometry`.
+    pub fn with_activations<R: Rng>(geometry: Geometry, 
+                                    activations: Vec<Box<dyn Activation>>, 
+                                    rng: &mut R) -> Network 
+    {
+        let initializers = vec![Initializer::StandardNormal; activations.len()];
+        Network::with_initializers(geometry, activations, &initializers, rng)
+    }
+
+    /// Like `with_activations`, drawing the parameters of each non-input 
+    /// layer with its own initializer.
+    pub fn with_initializers<R: Rng>(geometry: Geometry, 
+                                     activations: Vec<Box<dyn Activation>>, 
+                                     initializers: &[Initializer],
+                                     rng: &mut R) -> Network 
+    {
+        assert!(geometry.num_layers() > 1, 
+                "A network needs at least an input and an output layer");
+        assert_eq!(activations.len(), geometry.num_layers()-1);
+        assert_eq!(initializers.len(), geometry.num_layers()-1);
+
+        let mut weights = Vec::with_capacity(geometry.num_layers()-1);
+        let mut biases = Vec::with_capacity(geometry.num_layers()-1);
+
+        for (sizes, init) in geometry.layers().windows(2).zip(initializers) {
+            let (inputs, outputs) = (sizes[0], sizes[1]);
+            weights.push(init.weights(outputs, inputs, rng));
+            biases.push(init.biases(outputs, rng));
+        }
+
+        Network {
+            geometry,
+            weights,
+            biases,
+            activations,
+        }
+    }
+
+    /// Assembles a network from existing parameters, checking that every 
+    /// weight matrix and bias vector fits `geometry`.
+    pub fn from_parts(geometry: Geometry, 
+                      activations: Vec<Box<dyn Activation>>,
+                      weights: Vec<Matrix<f64>>, 
+                      biases: Vec<Vector<f64>>) -> Result<Network> 
+    {
+        let layers = geometry.layers();
+        if layers.len() < 2 || layers.contains(&0) {
+            return Err(NetError::InvalidGeometry(layers.to_vec()));
+        }
+        let num_weight_layers = layers.len()-1;
+        for &found in &[activations.len(), weights.len(), biases.len()] {
+            if found != num_weight_layers {
+                return Err(NetError::LayerCountMismatch { expected: num_weight_layers, found });
+            }
+        }
+
+        for layer in 0..num_weight_layers {
+            let expected = (layers[layer+1], layers[layer]);
+            let found = (weights[layer].rows(), weights[layer].cols());
+            if found != expected {
+                return Err(NetError::ShapeMismatch { layer, expected, found });
+            }
+            let found = (biases[layer].len(), 1);
+            if found != (expected.0, 1) {
+                return Err(NetError::ShapeMismatch { layer, expected: (expected.0, 1), found });
+            }
+        }
+
+        Ok(
+            Network {
+                geometry,
+                weights,
+                biases,
+                activations,
+            }
+        )
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+    pub fn num_layers(&self) -> usize {
+        self.geometry.num_layers()
+    }
+
+    /// The weights feeding layer `layer+1` from layer `layer`, with one row per 
+    /// neuron in layer `layer+1`.
+    pub fn weights(&self, layer: usize) -> &Matrix<f64> {
+        &self.weights[layer]
+    }
+    pub fn biases(&self, layer: usize) -> &Vector<f64> {
+        &self.biases[layer]
+    }
+    pub fn weights_mut(&mut self, layer: usize) -> &mut Matrix<f64> {
+        &mut self.weights[layer]
+    }
+    pub fn biases_mut(&mut self, layer: usize) -> &mut Vector<f64> {
+        &mut self.biases[layer]
+    }
+    /// Every parameter block as a flat slice: `weights(0)`, `biases(0)`,
+    /// `weights(1)`, ... This is the order `Gradients::block` uses.
+    pub fn parameters_mut(&mut self) -> Vec<&mut [f64]> {
+        let mut blocks = Vec::with_capacity(2 * self.weights.len());
+        for (w, b) in self.weights.iter_mut().zip(self.biases.iter_mut()) {
+            blocks.push(w.as_mut_slice());
+            blocks.push(b.as_mut_slice());
+        }
+        blocks
+    }
+    /// The activation applied to the output of `weights(layer)`.
+    pub fn activation(&self, layer: usize) -> &dyn Activation {
+        &*self.activations[layer]
+    }
+    pub fn set_activation(&mut self, layer: usize, activation: Box<dyn Activation>) {
+        self.activations[layer] = activation;
+    }
+
+    pub fn feed_forward(&self, input: &[f64]) -> Vec<f64> {
+        assert_eq!(input.len(), self.geometry.input_size());
+
+        let mut activation = Vector::from_slice(input);
+        for layer in 0..self.weights.len() {
+            let z = self.weighted_input(layer, &activation);
+            activation = self.activations[layer].apply(&z);
+        }
+        activation.into_vec()
+    }
+
+    /// Feeds every row of `inputs` forward at once, giving one row of output 
+    /// per input row.
+    pub fn feed_forward_batch(&self, inputs: &Matrix<f64>) -> Matrix<f64> {
+        assert_eq!(inputs.cols(), self.geometry.input_size());
+
+        let mut activations = inputs.clone();
+        for layer in 0..self.weights.len() {
+            let mut z = activations.mul(&self.weights[layer].transpose());
+            for row in 0..z.rows() {
+                let mut zr = Vector::from_slice(z.row(row));
+                zr.add_assign(&self.biases[layer]);
+                let a = self.activations[layer].apply(&zr);
+                z.row_mut(row).copy_from_slice(a.as_slice());
+            }
+            activations = z;
+        }
+        activations
+    }
+
+    /// Index of the most active output neuron for `input`.
+    pub fn classify(&self, input: &[f64]) -> usize {
+        argmax(&self.feed_forward(input))
+    }
+
+    /// Count of examples whose most active output matches the most active 
+    /// target, fed forward a batch at a time.
+    pub fn evaluate<E: Examples + ?Sized>(&self, data: &E) -> usize {
+        Batches::in_order(data, EVALUATION_BATCH_SIZE)
+            .map(|batch| {
+                let outputs = self.feed_forward_batch(&batch.inputs);
+                (0..outputs.rows())
+                    .filter(|&row| argmax(outputs.row(row)) == argmax(batch.targets.row(row)))
+                    .count()
+            })
+            .sum()
+    }
+
+    /// `z = w*a + b` for the layer fed by `layer`.
+    pub fn weighted_input(&self, layer: usize, activation: &Vector<f64>) -> Vector<f64> {
+        let mut z = self.weights[layer].mul_vec(activation);
+        z.add_assign(&self.biases[layer]);
+        z
+    }
+}
+
+pub fn argmax(values: &[f64]) -> usize {
+    let mut best = 0;
+    for (i, &x) in values.iter().enumerate() {
+        if x > values[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn feed_forward_shape() {
+        let net = Network::new(Geometry::new(vec![4, 3, 2]));
+        let output = net.feed_forward(&[0.0, 0.5, 1.0, 0.25]);
+
+        assert_eq!(output.len(), 2);
+        assert!(output.iter().all(|&x| x > 0.0 && x < 1.0));
+    }
+
+    #[test]
+    fn batch_matches_single_feed_forward() {
+        let net = Network::new(Geometry::new(vec![3, 4, 2]));
+        let inputs = Matrix::new(2, 3, vec![0.1, 0.2, 0.3, -1.0, 0.0, 2.0]);
+        let outputs = net.feed_forward_batch(&inputs);
+        for row in 0..2 {
+            let single = net.feed_forward(inputs.row(row));
+            for (a, b) in outputs.row(row).iter().zip(&single) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn per_layer_activations() {
+        use net::activation::{Relu, Softmax};
+
+        let activations: Vec<Box<dyn Activation>> = vec![Box::new(Relu), Box::new(Softmax)];
+        let net = Network::with_activations(Geometry::new(vec![4, 3, 2]), activations, 
+                                            &mut rand::thread_rng());
+        let output = net.feed_forward(&[0.0, 0.5, 1.0, 0.25]);
+
+        assert_eq!(net.activation(0).name(), "relu");
+        assert!((output.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,414 @@
+use std::fmt;
+
+use super::network::Network;
+use super::train::Gradients;
+
+/// Turns mini-batch gradients into parameter updates, keeping whatever per 
+/// parameter state the method needs between steps.
+pub trait Optimizer: fmt::Debug {
+    fn name(&self) -> &'static str;
+    /// Updates `net` given the mean gradient of a mini-batch.
+    fn step(&mut self, net: &mut Network, gradients: &Gradients, learning_rate: f64);
+    /// A snapshot of the optimizer, sufficient to resume with `from_state`.
+    fn state(&self) -> OptimizerState;
+    fn box_clone(&self) -> Box<dyn Optimizer>;
+}
+
+impl Clone for Box<dyn Optimizer> {
+    fn clone(&self) -> Box<dyn Optimizer> {
+        self.box_clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptimizerState {
+    pub name: String,
+    pub hyperparameters: Vec<f64>,
+    pub steps: u64,
+    /// Per-parameter buffers such as velocities or moment estimates, each 
+    /// shaped like the network's gradients.
+    pub slots: Vec<Gradients>,
+}
+
+/// Plain gradient descent, `w -= eta * g`.
+#[derive(Clone, Debug, Default)]
+pub struct GradientDescent;
+
+/// Classical momentum, `v = mu*v - eta*g; w += v`.
+#[derive(Clone, Debug)]
+pub struct Momentum {
+    pub momentum: f64,
+    velocity: Option<Gradients>,
+}
+
+/// Nesterov accelerated gradient, in the form that only needs the gradient 
+/// at the current parameters.
+#[derive(Clone, Debug)]
+pub struct Nesterov {
+    pub momentum: f64,
+    velocity: Option<Gradients>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AdaGrad {
+    pub epsilon: f64,
+    sum_sq: Option<Gradients>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RmsProp {
+    pub decay: f64,
+    pub epsilon: f64,
+    mean_sq: Option<Gradients>,
+}
+
+/// Adam, optionally with AdamW's decoupled weight decay applied to weights 
+/// (not biases) at every step.
+#[derive(Clone, Debug)]
+pub struct Adam {
+    pub beta1: f64,
+    pub beta2: f64,
+    pub epsilon: f64,
+    pub weight_decay: f64,
+    steps: u64,
+    moments: Option<(Gradients, Gradients)>,
+}
+
+/// Looks up an optimizer by its `name()`, with default hyperparameters.
+pub fn from_name(name: &str) -> Option<Box<dyn Optimizer>> {
+    let optimizer: Box<dyn Optimizer> = match name {
+        "sgd" => Box::new(GradientDescent),
+        "momentum" => Box::new(Momentum::new(0.9)),
+        "nesterov" => Box::new(Nesterov::new(0.9)),
+        "adagrad" => Box::new(AdaGrad::new()),
+        "rmsprop" => Box::new(RmsProp::new(0.9)),
+        "adam" => Box::new(Adam::new(0.9, 0.999)),
+        "adamw" => Box::new(Adam::adamw(0.9, 0.999, 0.01)),
+        _ => return None,
+    };
+    Some(optimizer)
+}
+
+/// Rebuilds an optimizer, including its accumulated state, from a snapshot.
+pub fn from_state(state: OptimizerState) -> Option<Box<dyn Optimizer>> {
+    let hyperparameters = state.hyperparameters;
+    let h = |i: usize| hyperparameters.get(i).cloned();
+    let mut slots = state.slots.into_iter();
+    let optimizer: Box<dyn Optimizer> = match state.name.as_str() {
+        "sgd" => Box::new(GradientDescent),
+        "momentum" => Box::new(Momentum { momentum: h(0)?, velocity: slots.next() }),
+        "nesterov" => Box::new(Nesterov { momentum: h(0)?, velocity: slots.next() }),
+        "adagrad" => Box::new(AdaGrad { epsilon: h(0)?, sum_sq: slots.next() }),
+        "rmsprop" => Box::new(RmsProp { decay: h(0)?, epsilon: h(1)?, mean_sq: slots.next() }),
+        "adam" | "adamw" => {
+            let moments = match (slots.next(), slots.next()) {
+                (Some(m), Some(v)) => Some((m, v)),
+                _ => None,
+            };
+            Box::new(Adam { 
+                beta1: h(0)?, beta2: h(1)?, epsilon: h(2)?, weight_decay: h(3)?, 
+                steps: state.steps, 
+                moments,
+            })
+        },
+        _ => return None,
+    };
+    Some(optimizer)
+}
+
+const EPSILON: f64 = 1e-8;
+
+/// Returns `slot`, replacing it with zeros if it does not match `net`.
+fn init_slot<'a>(slot: &'a mut Option<Gradients>, net: &Network) -> &'a mut Gradients {
+    if !slot.as_ref().is_some_and(|s| s.fits(net)) {
+        *slot = Some(Gradients::zeros(net));
+    }
+    slot.as_mut().unwrap()
+}
+
+fn slots(buffers: &[&Option<Gradients>]) -> Vec<Gradients> {
+    buffers.iter().filter_map(|b| (*b).clone()).collect()
+}
+
+impl Optimizer for GradientDescent {
+    fn name(&self) -> &'static str { "sgd" }
+    fn step(&mut self, net: &mut Network, gradients: &Gradients, learning_rate: f64) {
+        for (b, params) in net.parameters_mut().into_iter().enumerate() {
+            for (p, &g) in params.iter_mut().zip(gradients.block(b)) {
+                *p -= learning_rate * g;
+            }
+        }
+    }
+    fn state(&self) -> OptimizerState {
+        OptimizerState { name: self.name().to_string(), hyperparameters: vec![], steps: 0, slots: vec![] }
+    }
+    fn box_clone(&self) -> Box<dyn Optimizer> { Box::new(self.clone()) }
+}
+
+impl Momentum {
+    pub fn new(momentum: f64) -> Momentum {
+        Momentum { momentum, velocity: None }
+    }
+}
+
+impl Optimizer for Momentum {
+    fn name(&self) -> &'static str { "momentum" }
+    fn step(&mut self, net: &mut Network, gradients: &Gradients, learning_rate: f64) {
+        let velocity = init_slot(&mut self.velocity, net);
+        for (b, params) in net.parameters_mut().into_iter().enumerate() {
+            let v = velocity.block_mut(b);
+            for ((p, v), &g) in params.iter_mut().zip(v.iter_mut()).zip(gradients.block(b)) {
+                *v = self.momentum * *v - learning_rate * g;
+                *p += *v;
+            }
+        }
+    }
+    fn state(&self) -> OptimizerState {
+        OptimizerState { 
+            name: self.name().to_string(), 
+            hyperparameters: vec![self.momentum], 
+            steps: 0, 
+            slots: slots(&[&self.velocity]),
+        }
+    }
+    fn box_clone(&self) -> Box<dyn Optimizer> { Box::new(self.clone()) }
+}
+
+impl Nesterov {
+    pub fn new(momentum: f64) -> Nesterov {
+        Nesterov { momentum, velocity: None }
+    }
+}
+
+impl Optimizer for Nesterov {
+    fn name(&self) -> &'static str { "nesterov" }
+    fn step(&mut self, net: &mut Network, gradients: &Gradients, learning_rate: f64) {
+        let velocity = init_slot(&mut self.velocity, net);
+        let mu = self.momentum;
+        for (b, params) in net.parameters_mut().into_iter().enumerate() {
+            let v = velocity.block_mut(b);
+            for ((p, v), &g) in params.iter_mut().zip(v.iter_mut()).zip(gradients.block(b)) {
+                let prev = *v;
+                *v = mu * prev - learning_rate * g;
+                *p += -mu * prev + (1.0 + mu) * *v;
+            }
+        }
+    }
+    fn state(&self) -> OptimizerState {
+        OptimizerState { 
+            name: self.name().to_string(), 
+            hyperparameters: vec![self.momentum], 
+            steps: 0, 
+            slots: slots(&[&self.velocity]),
+        }
+    }
+    fn box_clone(&self) -> Box<dyn Optimizer> { Box::new(self.clone()) }
+}
+
+impl AdaGrad {
+    pub fn new() -> AdaGrad {
+        AdaGrad { epsilon: EPSILON, sum_sq: None }
+    }
+}
+
+impl Default for AdaGrad {
+    fn default() -> AdaGrad {
+        AdaGrad::new()
+    }
+}
+
+impl Optimizer for AdaGrad {
+    fn name(&self) -> &'static str { "adagrad" }
+    fn step(&mut self, net: &mut Network, gradients: &Gradients, learning_rate: f64) {
+        let sum_sq = init_slot(&mut self.sum_sq, net);
+        for (b, params) in net.parameters_mut().into_iter().enumerate() {
+            let s = sum_sq.block_mut(b);
+            for ((p, s), &g) in params.iter_mut().zip(s.iter_mut()).zip(gradients.block(b)) {
+                *s += g * g;
+                *p -= learning_rate * g / (s.sqrt() + self.epsilon);
+            }
+        }
+    }
+    fn state(&self) -> OptimizerState {
+        OptimizerState { 
+            name: self.name().to_string(), 
+            hyperparameters: vec![self.epsilon], 
+            steps: 0, 
+            slots: slots(&[&self.sum_sq]),
+        }
+    }
+    fn box_clone(&self) -> Box<dyn Optimizer> { Box::new(self.clone()) }
+}
+
+impl RmsProp {
+    pub fn new(decay: f64) -> RmsProp {
+        RmsProp { decay, epsilon: EPSILON, mean_sq: None }
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn name(&self) -> &'static str { "rmsprop" }
+    fn step(&mut self, net: &mut Network, gradients: &Gradients, learning_rate: f64) {
+        let mean_sq = init_slot(&mut self.mean_sq, net);
+        let rho = self.decay;
+        for (b, params) in net.parameters_mut().into_iter().enumerate() {
+            let s = mean_sq.block_mut(b);
+            for ((p, s), &g) in params.iter_mut().zip(s.iter_mut()).zip(gradients.block(b)) {
+                *s = rho * *s + (1.0 - rho) * g * g;
+                *p -= learning_rate * g / (s.sqrt() + self.epsilon);
+            }
+        }
+    }
+    fn state(&self) -> OptimizerState {
+        OptimizerState { 
+            name: self.name().to_string(), 
+            hyperparameters: vec![self.decay, self.epsilon], 
+            steps: 0, 
+            slots: slots(&[&self.mean_sq]),
+        }
+    }
+    fn box_clone(&self) -> Box<dyn Optimizer> { Box::new(self.clone()) }
+}
+
+impl Adam {
+    pub fn new(beta1: f64, beta2: f64) -> Adam {
+        Adam::adamw(beta1, beta2, 0.0)
+    }
+    pub fn adamw(beta1: f64, beta2: f64, weight_decay: f64) -> Adam {
+        Adam { beta1, beta2, epsilon: EPSILON, weight_decay, steps: 0, moments: None }
+    }
+}
+
+impl Optimizer for Adam {
+    fn name(&self) -> &'static str { 
+        if self.weight_decay == 0.0 { "adam" } else { "adamw" }
+    }
+    fn step(&mut self, net: &mut Network, gradients: &Gradients, learning_rate: f64) {
+        if !self.moments.as_ref().is_some_and(|(m, _)| m.fits(net)) {
+            self.moments = Some((Gradients::zeros(net), Gradients::zeros(net)));
+            self.steps = 0;
+        }
+        let (ref mut first, ref mut second) = *self.moments.as_mut().unwrap();
+        self.steps += 1;
+
+        let (b1, b2) = (self.beta1, self.beta2);
+        let correction1 = 1.0 - b1.powi(self.steps as i32);
+        let correction2 = 1.0 - b2.powi(self.steps as i32);
+        for (b, params) in net.parameters_mut().into_iter().enumerate() {
+            let is_weights = Gradients::is_weight_block(b);
+            let (m, v) = (first.block_mut(b), second.block_mut(b));
+            for (i, p) in params.iter_mut().enumerate() {
+                let g = gradients.block(b)[i];
+                m[i] = b1 * m[i] + (1.0 - b1) * g;
+                v[i] = b2 * v[i] + (1.0 - b2) * g * g;
+                let m_hat = m[i] / correction1;
+                let v_hat = v[i] / correction2;
+                if is_weights {
+                    *p -= learning_rate * self.weight_decay * *p;
+                }
+                *p -= learning_rate * m_hat / (v_hat.sqrt() + self.epsilon);
+            }
+        }
+    }
+    fn state(&self) -> OptimizerState {
+        let slots = match self.moments {
+            Some((ref m, ref v)) => vec![m.clone(), v.clone()],
+            None => vec![],
+        };
+        OptimizerState { 
+            name: self.name().to_string(), 
+            hyperparameters: vec![self.beta1, self.beta2, self.epsilon, self.weight_decay], 
+            steps: self.steps, 
+            slots,
+        }
+    }
+    fn box_clone(&self) -> Box<dyn Optimizer> { Box::new(self.clone()) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::{Matrix, Vector};
+    use rand::{SeedableRng, StdRng};
+    use net::geom::Geometry;
+
+    fn net() -> Network {
+        Network::with_rng(Geometry::new(vec![1, 1]), &mut StdRng::from_seed(&[1]))
+    }
+
+    fn grads(g: f64) -> Gradients {
+        Gradients { 
+            weights: vec![Matrix::new(1, 1, vec![g])], 
+            biases: vec![Vector::new(vec![0.0])],
+        }
+    }
+
+    fn weight(net: &Network) -> f64 {
+        net.weights(0)[(0, 0)]
+    }
+
+    #[test]
+    fn momentum_accumulates_velocity() {
+        let mut net = net();
+        let w0 = weight(&net);
+        let mut opt = Momentum::new(0.5);
+        opt.step(&mut net, &grads(1.0), 0.1);
+        opt.step(&mut net, &grads(1.0), 0.1);
+        // v1 = -0.1, v2 = 0.5*-0.1 - 0.1 = -0.15
+        assert!((weight(&net) - (w0 - 0.25)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn nesterov_first_steps() {
+        let mut net = net();
+        let w0 = weight(&net);
+        let mut opt = Nesterov::new(0.5);
+        opt.step(&mut net, &grads(1.0), 0.1);
+        // v1 = -0.1, w += 1.5 * -0.1
+        assert!((weight(&net) - (w0 - 0.15)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn adaptive_methods_first_step() {
+        // AdaGrad, RMSProp and Adam all take a step of about eta on the first 
+        // update, regardless of the gradient's magnitude.
+        let opts: Vec<Box<dyn Optimizer>> = vec![
+            Box::new(AdaGrad::new()), 
+            Box::new(RmsProp { decay: 0.0, epsilon: EPSILON, mean_sq: None }), 
+            Box::new(Adam::new(0.9, 0.999)),
+        ];
+        for mut opt in opts {
+            let mut net = net();
+            let w0 = weight(&net);
+            opt.step(&mut net, &grads(250.0), 0.01);
+            assert!((weight(&net) - (w0 - 0.01)).abs() < 1e-8, "{}", opt.name());
+        }
+    }
+
+    #[test]
+    fn adamw_decays_weights_only() {
+        let mut net = net();
+        let (w0, b0) = (weight(&net), net.biases(0)[0]);
+        let mut opt = Adam::adamw(0.9, 0.999, 0.5);
+        let zero = Gradients { weights: vec![Matrix::zeros(1, 1)], biases: vec![Vector::zeros(1)] };
+        opt.step(&mut net, &zero, 0.1);
+        assert!((weight(&net) - w0 * 0.95).abs() < 1e-12);
+        assert_eq!(net.biases(0)[0], b0);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        for name in &["sgd", "momentum", "nesterov", "adagrad", "rmsprop", "adam", "adamw"] {
+            let mut a = from_name(name).unwrap();
+            let mut net_a = net();
+            a.step(&mut net_a, &grads(0.3), 0.1);
+
+            let mut b = from_state(a.state()).unwrap();
+            let mut net_b = net_a.clone();
+            a.step(&mut net_a, &grads(-0.2), 0.1);
+            b.step(&mut net_b, &grads(-0.2), 0.1);
+            assert_eq!(net_a.weights(0), net_b.weights(0), "{}", name);
+            assert_eq!(a.state(), b.state());
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use math::Matrix;
+use super::network::Network;
+
+/// Weight penalties added to the cost. Strengths are divided by the size of 
+/// the training set, so the same `lambda` regularizes equally strongly when 
+/// the amount of data changes. Biases are never penalized.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Regularization {
+    #[default]
+    None,
+    /// `lambda/n * sum |w|`
+    L1(f64),
+    /// Weight decay, `lambda/(2n) * sum w^2`
+    L2(f64),
+    /// Both penalties at once.
+    ElasticNet { l1: f64, l2: f64 },
+}
+
+impl Regularization {
+    fn strengths(&self) -> (f64, f64) {
+        match *self {
+            Regularization::None => (0.0, 0.0),
+            Regularization::L1(l1) => (l1, 0.0),
+            Regularization::L2(l2) => (0.0, l2),
+            Regularization::ElasticNet { l1, l2 } => (l1, l2),
+        }
+    }
+
+    /// The penalty term added to the mean cost for a training set of `n` items.
+    pub fn penalty(&self, net: &Network, n: usize) -> f64 {
+        let (l1, l2) = self.strengths();
+        if l1 == 0.0 && l2 == 0.0 {
+            return 0.0;
+        }
+
+        let (mut abs_sum, mut sq_sum) = (0.0, 0.0);
+        for layer in 0..net.num_layers()-1 {
+            for &w in net.weights(layer).as_slice() {
+                abs_sum += w.abs();
+                sq_sum += w * w;
+            }
+        }
+        (l1 * abs_sum + 0.5 * l2 * sq_sum) / n as f64
+    }
+
+    /// Adds the gradient of the penalty with respect to `weights` to 
+    /// `gradient`, so any optimizer sees it as part of the cost.
+    pub fn add_gradient(&self, weights: &Matrix<f64>, gradient: &mut Matrix<f64>, n: usize) {
+        let (l1, l2) = self.strengths();
+        if l1 == 0.0 && l2 == 0.0 {
+            return;
+        }
+        let (l1, l2) = (l1 / n as f64, l2 / n as f64);
+        for (g, &w) in gradient.as_mut_slice().iter_mut().zip(weights.as_slice()) {
+            let sign = if w > 0.0 { 1.0 } else if w < 0.0 { -1.0 } else { 0.0 };
+            *g += l2 * w + l1 * sign;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::Vector;
+    use net::activation::{Activation, Sigmoid};
+    use net::geom::Geometry;
+
+    #[test]
+    fn penalty() {
+        let activations: Vec<Box<dyn Activation>> = vec![Box::new(Sigmoid)];
+        let net = |biases: Vec<f64>| Network::from_parts(
+            Geometry::new(vec![3, 1]), activations.clone(),
+            vec![Matrix::new(1, 3, vec![2.0, -1.0, 0.0])], vec![Vector::new(biases)]).unwrap();
+        let (net, biased) = (net(vec![0.0]), net(vec![4.0]));
+
+        for &(r, expected) in &[(Regularization::L2(5.0), 1.25),
+                                (Regularization::L1(2.0), 0.6),
+                                (Regularization::ElasticNet { l1: 2.0, l2: 5.0 }, 1.85),
+                                (Regularization::None, 0.0)] {
+            assert!((r.penalty(&net, 10) - expected).abs() < 1e-12, "{:?}", r);
+            assert_eq!(r.penalty(&biased, 10), r.penalty(&net, 10));
+            assert!((r.penalty(&net, 5) - 2.0 * expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn gradient() {
+        let w = Matrix::new(1, 3, vec![2.0, -1.0, 0.0]);
+        let grad = |r: Regularization| {
+            let mut g = Matrix::zeros(1, 3);
+            r.add_gradient(&w, &mut g, 10);
+            g.as_slice().to_vec()
+        };
+
+        assert_eq!(grad(Regularization::L2(5.0)), vec![1.0, -0.5, 0.0]);
+        assert_eq!(grad(Regularization::L1(2.0)), vec![0.2, -0.2, 0.0]);
+        assert_eq!(grad(Regularization::ElasticNet { l1: 2.0, l2: 5.0 }), vec![1.2, -0.7, 0.0]);
+        assert_eq!(grad(Regularization::None), vec![0.0, 0.0, 0.0]);
+    }
+}
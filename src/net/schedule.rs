@@ -0,0 +1,212 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the learning rate changes over the epochs of a training run.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Schedule {
+    #[default]
+    Constant,
+    /// Multiplies the rate by `factor` every `every` epochs.
+    Step { every: usize, factor: f64 },
+    /// `rate * gamma^epoch`
+    Exponential { gamma: f64 },
+    /// Cosine annealing from the base rate down to `min_rate` over `period`
+    /// epochs, then a restart with the period multiplied by `multiplier`.
+    CosineRestarts { period: usize, multiplier: usize, min_rate: f64 },
+    /// Multiplies the rate by `factor` whenever the validation metric has
+    /// not improved for `patience` epochs.
+    Plateau { patience: usize, factor: f64 },
+}
+
+/// Tracks the learning rate of a `Schedule` as training progresses.
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    schedule: Schedule,
+    base_rate: f64,
+    plateau_scale: f64,
+    best: Option<f64>,
+    stale_epochs: usize,
+}
+
+/// Stops training once the validation metric has not improved by more than
+/// `min_delta` for `patience` epochs. The best network seen is restored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EarlyStopping {
+    pub patience: usize,
+    pub min_delta: f64,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Schedule, base_rate: f64) -> Scheduler {
+        Scheduler { schedule, base_rate, plateau_scale: 1.0, best: None, stale_epochs: 0 }
+    }
+
+    /// The learning rate for `epoch`, counting from zero.
+    pub fn rate(&self, epoch: usize) -> f64 {
+        let base = self.base_rate;
+        match self.schedule {
+            Schedule::Constant => base,
+            Schedule::Step { every, factor } => base * factor.powi((epoch / every.max(1)) as i32),
+            Schedule::Exponential { gamma } => base * gamma.powi(epoch as i32),
+            Schedule::CosineRestarts { period, multiplier, min_rate } => {
+                // A cycle whose end would overflow never ends.
+                let (mut start, mut length) = (0usize, period.max(1));
+                while let Some(end) = start.checked_add(length).filter(|&end| epoch >= end) {
+                    start = end;
+                    length = length.saturating_mul(multiplier.max(1));
+                }
+                let progress = (epoch - start) as f64 / length as f64;
+                min_rate + 0.5 * (base - min_rate) * (1.0 + (PI * progress).cos())
+            },
+            Schedule::Plateau { .. } => base * self.plateau_scale,
+        }
+    }
+
+    /// Records the validation metric at the end of an epoch, where higher is
+    /// better. Only `Schedule::Plateau` uses it.
+    pub fn observe(&mut self, metric: f64) {
+        if let Schedule::Plateau { patience, factor } = self.schedule {
+            if self.best.is_none_or(|best| metric > best) {
+                self.best = Some(metric);
+                self.stale_epochs = 0;
+            } else {
+                self.stale_epochs += 1;
+                if self.stale_epochs >= patience {
+                    self.plateau_scale *= factor;
+                    self.stale_epochs = 0;
+                }
+            }
+        }
+    }
+}
+
+impl EarlyStopping {
+    pub fn new(patience: usize) -> EarlyStopping {
+        EarlyStopping { patience, min_delta: 0.0 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseScheduleError(String);
+
+impl FromStr for Schedule {
+    type Err = ParseScheduleError;
+
+    /// Parses `constant`, `step:EVERY:FACTOR`, `exp:GAMMA`,
+    /// `cosine:PERIOD[:MULTIPLIER[:MIN_RATE]]` or `plateau:PATIENCE[:FACTOR]`.
+    fn from_str(s: &str) -> Result<Schedule, ParseScheduleError> {
+        let mut parts = s.split(':');
+        let kind = parts.next().unwrap_or("");
+        let args: Vec<&str> = parts.collect();
+        let arg = |i: usize, default: Option<&str>| -> Result<f64, ParseScheduleError> {
+            let value = args.get(i).cloned().or(default)
+                .ok_or_else(|| ParseScheduleError(format!("'{}' needs more arguments", kind)))?;
+            value.parse::<f64>()
+                .ok().filter(|x| x.is_finite() && *x >= 0.0)
+                .ok_or_else(|| ParseScheduleError(format!("invalid argument '{}'", value)))
+        };
+        let count = |i: usize, default: Option<&str>| -> Result<usize, ParseScheduleError> {
+            match arg(i, default)? {
+                x if x >= 1.0 && x.fract() == 0.0 => Ok(x as usize),
+                x => Err(ParseScheduleError(format!("expected a positive integer, found {}", x))),
+            }
+        };
+
+        let (schedule, max_args) = match kind {
+            "constant" => (Schedule::Constant, 0),
+            "step" => (Schedule::Step { every: count(0, None)?, factor: arg(1, None)? }, 2),
+            "exp" => (Schedule::Exponential { gamma: arg(0, None)? }, 1),
+            "cosine" => (Schedule::CosineRestarts {
+                period: count(0, None)?,
+                multiplier: count(1, Some("1"))?,
+                min_rate: arg(2, Some("0"))?,
+            }, 3),
+            "plateau" => (Schedule::Plateau {
+                patience: count(0, None)?,
+                factor: arg(1, Some("0.5"))?,
+            }, 2),
+            _ => return Err(ParseScheduleError(format!("unknown schedule '{}'", kind))),
+        };
+        if args.len() > max_args {
+            return Err(ParseScheduleError(format!("too many arguments for '{}'", kind)));
+        }
+        Ok(schedule)
+    }
+}
+
+impl fmt::Display for ParseScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rates(schedule: Schedule, epochs: usize) -> Vec<f64> {
+        let scheduler = Scheduler::new(schedule, 1.0);
+        (0..epochs).map(|e| scheduler.rate(e)).collect()
+    }
+
+    #[test]
+    fn fixed_schedules() {
+        assert_eq!(rates(Schedule::Constant, 3), vec![1.0, 1.0, 1.0]);
+        assert_eq!(rates(Schedule::Step { every: 2, factor: 0.5 }, 5),
+                   vec![1.0, 1.0, 0.5, 0.5, 0.25]);
+        assert_eq!(rates(Schedule::Exponential { gamma: 0.5 }, 3), vec![1.0, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn cosine_restarts() {
+        let cosine = Schedule::CosineRestarts { period: 2, multiplier: 2, min_rate: 0.0 };
+        let r = rates(cosine, 7);
+        // Cycles cover epochs [0, 2) and [2, 6), then restart at 6.
+        let expected = [1.0, 0.5, 1.0, 0.8535533905932737, 0.5, 0.14644660940672627, 1.0];
+        for (a, b) in r.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 1e-12, "{:?}", r);
+        }
+    }
+
+    #[test]
+    fn cosine_restarts_with_huge_multiplier() {
+        let cosine: Schedule = "cosine:5:10000000000000000000".parse().unwrap();
+        let scheduler = Scheduler::new(cosine, 1.0);
+        assert_eq!(scheduler.rate(5), 1.0);
+        // The second cycle is too long to end, so it anneals over the rest 
+        // of the epochs.
+        for &(epoch, expected) in &[(6, 1.0), (1000, 1.0), (usize::MAX / 2, 0.5), (usize::MAX, 0.0)] {
+            let rate = scheduler.rate(epoch);
+            assert!((rate - expected).abs() < 1e-9, "{} at {}", rate, epoch);
+        }
+        let huge = Scheduler::new(Schedule::CosineRestarts { 
+            period: usize::MAX, multiplier: usize::MAX, min_rate: 0.0 }, 1.0);
+        assert_eq!(huge.rate(usize::MAX), 1.0);
+    }
+
+    #[test]
+    fn plateau_halves_rate() {
+        let mut scheduler = Scheduler::new(Schedule::Plateau { patience: 2, factor: 0.5 }, 1.0);
+        for &metric in &[0.5, 0.6, 0.6, 0.55] {
+            scheduler.observe(metric);
+        }
+        assert_eq!(scheduler.rate(4), 0.5);
+        scheduler.observe(0.7);
+        scheduler.observe(0.7);
+        assert_eq!(scheduler.rate(6), 0.5);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("constant".parse(), Ok(Schedule::Constant));
+        assert_eq!("step:10:0.1".parse(), Ok(Schedule::Step { every: 10, factor: 0.1 }));
+        assert_eq!("cosine:5".parse(),
+                   Ok(Schedule::CosineRestarts { period: 5, multiplier: 1, min_rate: 0.0 }));
+        assert_eq!("plateau:3".parse(), Ok(Schedule::Plateau { patience: 3, factor: 0.5 }));
+        assert!("step:10".parse::<Schedule>().is_err());
+        assert!("exp:0.9:1".parse::<Schedule>().is_err());
+        assert!("cosine:0".parse::<Schedule>().is_err());
+        assert!("linear".parse::<Schedule>().is_err());
+    }
+}
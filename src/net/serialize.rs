@@ -0,0 +1,636 @@
+//! Saving and loading trained networks.
+//!
+//! # Binary format
+//!
+//! All integers and floats are big-endian.
+//!
+//! | Field          | Encoding                                 |
+//! |----------------|------------------------------------------|
+//! | magic          | the four bytes `NNET`                    |
+//! | version        | `u16`, currently 2                       |
+//! | reserved       | `u16`, zero                              |
+//! | payload length | `u64`                                    |
+//! | payload        | see below                                |
+//! | checksum       | `u32`, CRC-32 (IEEE) of the payload      |
+//!
+//! The payload holds the geometry as a `u32` layer count followed by each 
+//! layer size as `u32`. Then, for every layer after the input:
+//!
+//! - the activation name as a `u8` length and UTF-8 bytes, then a `u8` 
+//!   parameter count and that many `f64` parameters,
+//! - the weight matrix as `u32` rows, `u32` columns and `rows*cols` `f64` 
+//!   values in row-major order,
+//! - the biases as a `u32` length and that many `f64` values.
+//!
+//! Since version 2 the payload ends with a `u8` flag saying whether optimizer 
+//! state follows. If it does, it is stored as the optimizer name (`u8` length 
+//! and bytes), a `u8` hyperparameter count and that many `f64`s, a `u64` step 
+//! count, a `u8` slot count and then, for each slot, the `f64` values of every 
+//! weight matrix and bias vector in the order `weights(0)`, `biases(0)`, ... 
+//! Version 1 files, which end after the last layer, are still read.
+//!
+//! # JSON format
+//!
+//! The same information as an object with `format`, `version`, `geometry` 
+//! and a `layers` array of `{activation: {name, params}, weights: {rows, 
+//! cols, data}, biases}` objects, plus an optional `optimizer` object of 
+//! `{name, hyperparameters, steps, slots}` where each slot is an array of 
+//! parameter blocks.
+
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use math::{Matrix, Vector};
+use super::activation::{self, Activation};
+use super::error::{NetError, Result};
+use super::geom::Geometry;
+use super::json::Json;
+use super::network::Network;
+use super::optimizer::{self, Optimizer, OptimizerState};
+use super::train::Gradients;
+
+pub const MAGIC: [u8; 4] = *b"NNET";
+pub const VERSION: u16 = 2;
+const JSON_FORMAT: &str = "neural_net";
+
+/// A network together with the optimizer training it, so that training can 
+/// be resumed where it stopped.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub network: Network,
+    pub optimizer: Option<Box<dyn Optimizer>>,
+}
+
+impl Network {
+    pub fn save(&self, file_name: &path::Path) -> Result<()> {
+        save(self, None, file_name)
+    }
+    pub fn save_json(&self, file_name: &path::Path) -> Result<()> {
+        fs::write(file_name, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Loads a network saved in either the binary or the JSON format, 
+    /// ignoring any optimizer state stored with it.
+    pub fn load(file_name: &path::Path) -> Result<Network> {
+        Checkpoint::load(file_name).map(|c| c.network)
+    }
+
+    pub fn write_binary<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_binary(self, None, writer)
+    }
+
+    pub fn read_binary<R: Read>(reader: &mut R) -> Result<Network> {
+        Checkpoint::read_binary(reader).map(|c| c.network)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        to_json(self, None)
+    }
+
+    pub fn from_json(text: &str) -> Result<Network> {
+        Checkpoint::from_json(text).map(|c| c.network)
+    }
+}
+
+impl Checkpoint {
+    pub fn new(network: Network, optimizer: Option<Box<dyn Optimizer>>) -> Checkpoint {
+        Checkpoint { network, optimizer }
+    }
+
+    pub fn save(&self, file_name: &path::Path) -> Result<()> {
+        save(&self.network, self.state().as_ref(), file_name)
+    }
+    pub fn save_json(&self, file_name: &path::Path) -> Result<()> {
+        fs::write(file_name, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Loads a checkpoint saved in either the binary or the JSON format.
+    pub fn load(file_name: &path::Path) -> Result<Checkpoint> {
+        let bytes = fs::read(file_name)?;
+        if bytes.starts_with(&MAGIC) {
+            Checkpoint::read_binary(&mut &bytes[..])
+        } else {
+            let text = String::from_utf8(bytes).map_err(|_| NetError::InvalidMagic)?;
+            Checkpoint::from_json(&text)
+        }
+    }
+
+    pub fn write_binary<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_binary(&self.network, self.state().as_ref(), writer)
+    }
+
+    pub fn read_binary<R: Read>(reader: &mut R) -> Result<Checkpoint> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(NetError::InvalidMagic);
+        }
+        let version = reader.read_u16::<BigEndian>()?;
+        if version == 0 || version > VERSION {
+            return Err(NetError::UnsupportedVersion { found: version, supported: VERSION });
+        }
+        reader.read_u16::<BigEndian>()?;
+
+        let len = reader.read_u64::<BigEndian>()?;
+        let mut payload = Vec::new();
+        reader.take(len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let expected = reader.read_u32::<BigEndian>()?;
+        let found = crc32(&payload);
+        if expected != found {
+            return Err(NetError::ChecksumMismatch { expected, found });
+        }
+
+        let mut payload = &payload[..];
+        let network = Network::read_payload(&mut payload)?;
+        let optimizer = if version >= 2 && payload.read_u8()? != 0 {
+            Some(read_optimizer(&mut payload, &network)?)
+        } else {
+            None
+        };
+        // A header that under-describes the layers would otherwise leave some 
+        // of the checksummed data unread.
+        if !payload.is_empty() {
+            return Err(NetError::TrailingData(payload.len()));
+        }
+        Checkpoint::from_state(network, optimizer)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        to_json(&self.network, self.state().as_ref())
+    }
+
+    pub fn from_json(text: &str) -> Result<Checkpoint> {
+        let root = Json::parse(text)?;
+        if root.get("format").and_then(Json::as_str) != Some(JSON_FORMAT) {
+            return Err(NetError::InvalidMagic);
+        }
+        let version = root.get("version").and_then(Json::as_f64).ok_or_else(|| missing("version"))?;
+        if version != 1.0 && version != 2.0 {
+            return Err(NetError::UnsupportedVersion { found: version as u16, supported: VERSION });
+        }
+
+        let geometry = Geometry::new(json_numbers(root.get("geometry"), "geometry")?
+            .into_iter().map(|x| x as usize).collect());
+        let layers = root.get("layers").and_then(Json::as_array).ok_or_else(|| missing("layers"))?;
+
+        let mut activations = Vec::new();
+        let mut weights = Vec::new();
+        let mut biases = Vec::new();
+        for (i, layer) in layers.iter().enumerate() {
+            let act = layer.get("activation").ok_or_else(|| missing("activation"))?;
+            let name = act.get("name").and_then(Json::as_str).ok_or_else(|| missing("name"))?;
+            let params = json_numbers(act.get("params"), "params")?;
+            activations.push(lookup_activation(name, &params)?);
+
+            let w = layer.get("weights").ok_or_else(|| missing("weights"))?;
+            let rows = w.get("rows").and_then(Json::as_f64).ok_or_else(|| missing("rows"))? as usize;
+            let cols = w.get("cols").and_then(Json::as_f64).ok_or_else(|| missing("cols"))? as usize;
+            weights.push(matrix(i, rows, cols, json_numbers(w.get("data"), "data")?)?);
+            biases.push(Vector::new(json_numbers(layer.get("biases"), "biases")?));
+        }
+
+        let network = Network::from_parts(geometry, activations, weights, biases)?;
+        let optimizer = match root.get("optimizer") {
+            Some(opt) => Some(optimizer_from_json(opt, &network)?),
+            None => None,
+        };
+        Checkpoint::from_state(network, optimizer)
+    }
+
+    fn state(&self) -> Option<OptimizerState> {
+        self.optimizer.as_ref().map(|o| o.state())
+    }
+
+    fn from_state(network: Network, state: Option<OptimizerState>) -> Result<Checkpoint> {
+        let optimizer = match state {
+            Some(state) => {
+                let name = state.name.clone();
+                Some(optimizer::from_state(state).ok_or(NetError::UnknownOptimizer(name))?)
+            },
+            None => None,
+        };
+        Ok(Checkpoint { network, optimizer })
+    }
+}
+
+fn save(net: &Network, state: Option<&OptimizerState>, file_name: &path::Path) -> Result<()> {
+    let mut writer = io::BufWriter::new(fs::File::create(file_name)?);
+    write_binary(net, state, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_binary<W: Write>(net: &Network, state: Option<&OptimizerState>, 
+                          writer: &mut W) -> Result<()> 
+{
+    let mut payload = Vec::new();
+    net.write_payload(&mut payload)?;
+    match state {
+        Some(state) => {
+            payload.write_u8(1)?;
+            write_optimizer(&mut payload, state)?;
+        },
+        None => payload.write_u8(0)?,
+    }
+
+    writer.write_all(&MAGIC)?;
+    writer.write_u16::<BigEndian>(VERSION)?;
+    writer.write_u16::<BigEndian>(0)?;
+    writer.write_u64::<BigEndian>(payload.len() as u64)?;
+    writer.write_all(&payload)?;
+    writer.write_u32::<BigEndian>(crc32(&payload))?;
+    Ok(())
+}
+
+fn to_json(net: &Network, state: Option<&OptimizerState>) -> Result<String> {
+    let mut layers = Vec::new();
+    for layer in 0..net.num_layers()-1 {
+        let act = net.activation(layer);
+        let weights = net.weights(layer);
+        layers.push(Json::Object(vec![
+            ("activation".to_string(), Json::Object(vec![
+                ("name".to_string(), Json::String(act.name().to_string())),
+                ("params".to_string(), json_array(&act.params())?),
+            ])),
+            ("weights".to_string(), Json::Object(vec![
+                ("rows".to_string(), Json::Number(weights.rows() as f64)),
+                ("cols".to_string(), Json::Number(weights.cols() as f64)),
+                ("data".to_string(), json_array(weights.as_slice())?),
+            ])),
+            ("biases".to_string(), json_array(net.biases(layer).as_slice())?),
+        ]));
+    }
+
+    let geometry = net.geometry().layers().iter().map(|&n| Json::Number(n as f64)).collect();
+    let mut fields = vec![
+        ("format".to_string(), Json::String(JSON_FORMAT.to_string())),
+        ("version".to_string(), Json::Number(VERSION as f64)),
+        ("geometry".to_string(), Json::Array(geometry)),
+        ("layers".to_string(), Json::Array(layers)),
+    ];
+    if let Some(state) = state {
+        let mut slots = Vec::new();
+        for slot in &state.slots {
+            let blocks = (0..slot.num_blocks()).map(|b| json_array(slot.block(b)));
+            slots.push(Json::Array(blocks.collect::<Result<_>>()?));
+        }
+        fields.push(("optimizer".to_string(), Json::Object(vec![
+            ("name".to_string(), Json::String(state.name.clone())),
+            ("hyperparameters".to_string(), json_array(&state.hyperparameters)?),
+            ("steps".to_string(), Json::Number(state.steps as f64)),
+            ("slots".to_string(), Json::Array(slots)),
+        ])));
+    }
+    Ok(Json::Object(fields).to_string_pretty())
+}
+
+impl Network {
+    fn write_payload(&self, out: &mut Vec<u8>) -> Result<()> {
+        let layers = self.geometry().layers();
+        out.write_u32::<BigEndian>(layers.len() as u32)?;
+        for &size in layers {
+            out.write_u32::<BigEndian>(size as u32)?;
+        }
+
+        for layer in 0..layers.len()-1 {
+            let act = self.activation(layer);
+            let params = act.params();
+            out.write_u8(act.name().len() as u8)?;
+            out.write_all(act.name().as_bytes())?;
+            out.write_u8(params.len() as u8)?;
+            write_f64s(out, &params)?;
+
+            let weights = self.weights(layer);
+            out.write_u32::<BigEndian>(weights.rows() as u32)?;
+            out.write_u32::<BigEndian>(weights.cols() as u32)?;
+            write_f64s(out, weights.as_slice())?;
+
+            let biases = self.biases(layer);
+            out.write_u32::<BigEndian>(biases.len() as u32)?;
+            write_f64s(out, biases.as_slice())?;
+        }
+        Ok(())
+    }
+
+    fn read_payload(reader: &mut &[u8]) -> Result<Network> {
+        let num_layers = reader.read_u32::<BigEndian>()? as usize;
+        let mut layers = Vec::new();
+        for _ in 0..num_layers {
+            layers.push(reader.read_u32::<BigEndian>()? as usize);
+        }
+        let geometry = Geometry::new(layers);
+
+        let mut activations = Vec::new();
+        let mut weights = Vec::new();
+        let mut biases = Vec::new();
+        for layer in 0..num_layers.saturating_sub(1) {
+            let name = read_name(reader)?;
+            let num_params = reader.read_u8()? as usize;
+            let params = read_f64s(reader, num_params)?;
+            activations.push(lookup_activation(&name, &params)?);
+
+            let rows = reader.read_u32::<BigEndian>()? as usize;
+            let cols = reader.read_u32::<BigEndian>()? as usize;
+            let data = read_f64s(reader, rows.saturating_mul(cols))?;
+            weights.push(matrix(layer, rows, cols, data)?);
+
+            let len = reader.read_u32::<BigEndian>()? as usize;
+            biases.push(Vector::new(read_f64s(reader, len)?));
+        }
+
+        Network::from_parts(geometry, activations, weights, biases)
+    }
+}
+
+fn write_optimizer(out: &mut Vec<u8>, state: &OptimizerState) -> Result<()> {
+    out.write_u8(state.name.len() as u8)?;
+    out.write_all(state.name.as_bytes())?;
+    out.write_u8(state.hyperparameters.len() as u8)?;
+    write_f64s(out, &state.hyperparameters)?;
+    out.write_u64::<BigEndian>(state.steps)?;
+    out.write_u8(state.slots.len() as u8)?;
+    for slot in &state.slots {
+        for b in 0..slot.num_blocks() {
+            write_f64s(out, slot.block(b))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads optimizer state whose slots are shaped like the parameters of `net`.
+fn read_optimizer(reader: &mut &[u8], net: &Network) -> Result<OptimizerState> {
+    let name = read_name(reader)?;
+    let num_hyperparameters = reader.read_u8()? as usize;
+    let hyperparameters = read_f64s(reader, num_hyperparameters)?;
+    let steps = reader.read_u64::<BigEndian>()?;
+    let num_slots = reader.read_u8()?;
+    let mut slots = Vec::new();
+    for _ in 0..num_slots {
+        let mut slot = Gradients::zeros(net);
+        for b in 0..slot.num_blocks() {
+            let block = slot.block_mut(b);
+            let values = read_f64s(reader, block.len())?;
+            block.copy_from_slice(&values);
+        }
+        slots.push(slot);
+    }
+    Ok(OptimizerState { name, hyperparameters, steps, slots })
+}
+
+fn optimizer_from_json(value: &Json, net: &Network) -> Result<OptimizerState> {
+    let name = value.get("name").and_then(Json::as_str).ok_or_else(|| missing("name"))?;
+    let hyperparameters = json_numbers(value.get("hyperparameters"), "hyperparameters")?;
+    let steps = value.get("steps").and_then(Json::as_f64).ok_or_else(|| missing("steps"))?;
+    let json_slots = value.get("slots").and_then(Json::as_array).ok_or_else(|| missing("slots"))?;
+
+    let mut slots = Vec::new();
+    for json_slot in json_slots {
+        let blocks = json_slot.as_array().ok_or_else(|| missing("slots"))?;
+        let mut slot = Gradients::zeros(net);
+        if blocks.len() != slot.num_blocks() {
+            return Err(NetError::LayerCountMismatch { 
+                expected: net.num_layers()-1, 
+                found: blocks.len() / 2,
+            });
+        }
+        for (b, block) in blocks.iter().enumerate() {
+            let values = json_numbers(Some(block), "slots")?;
+            let target = slot.block_mut(b);
+            if values.len() != target.len() {
+                return Err(NetError::ShapeMismatch { 
+                    layer: b / 2, 
+                    expected: (target.len(), 1), 
+                    found: (values.len(), 1),
+                });
+            }
+            target.copy_from_slice(&values);
+        }
+        slots.push(slot);
+    }
+    Ok(OptimizerState { name: name.to_string(), hyperparameters, steps: steps as u64, slots })
+}
+
+fn read_name(reader: &mut &[u8]) -> Result<String> {
+    let len = reader.read_u8()? as usize;
+    let mut name = vec![0; len];
+    reader.read_exact(&mut name)?;
+    Ok(String::from_utf8_lossy(&name).into_owned())
+}
+
+fn json_array(values: &[f64]) -> Result<Json> {
+    values.iter()
+        .map(|&x| if x.is_finite() { Ok(Json::Number(x)) } else { Err(NetError::NonFiniteValue) })
+        .collect::<Result<_>>()
+        .map(Json::Array)
+}
+
+fn write_f64s<W: Write>(writer: &mut W, values: &[f64]) -> Result<()> {
+    for &x in values {
+        writer.write_f64::<BigEndian>(x)?;
+    }
+    Ok(())
+}
+
+/// Reads `count` values, refusing up front if the payload is too short to 
+/// hold them rather than allocating for a corrupt count.
+fn read_f64s(reader: &mut &[u8], count: usize) -> Result<Vec<f64>> {
+    if reader.len() / 8 < count {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    let mut values = Vec::with_capacity(count);
+    for _ in 0..count {
+        values.push(reader.read_f64::<BigEndian>()?);
+    }
+    Ok(values)
+}
+
+fn matrix(layer: usize, rows: usize, cols: usize, data: Vec<f64>) -> Result<Matrix<f64>> {
+    if rows.checked_mul(cols) != Some(data.len()) {
+        return Err(NetError::ShapeMismatch { 
+            layer, 
+            expected: (rows, cols), 
+            found: (data.len(), 1),
+        });
+    }
+    Ok(Matrix::new(rows, cols, data))
+}
+
+fn lookup_activation(name: &str, params: &[f64]) -> Result<Box<dyn Activation>> {
+    activation::from_name(name, params).ok_or_else(|| NetError::UnknownActivation(name.to_string()))
+}
+
+fn json_numbers(value: Option<&Json>, field: &str) -> Result<Vec<f64>> {
+    value.and_then(Json::as_array)
+        .and_then(|values| values.iter().map(Json::as_f64).collect())
+        .ok_or_else(|| missing(field))
+}
+
+fn missing(field: &str) -> NetError {
+    NetError::Json { offset: 0, message: format!("missing or invalid field '{}'", field) }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+
+    !bytes.iter().fold(!0u32, |crc, &b| table[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use net::activation::{LeakyRelu, Softmax};
+    use net::optimizer::Adam;
+
+    fn network() -> Network {
+        let activations: Vec<Box<dyn Activation>> = 
+            vec![Box::new(LeakyRelu::new(0.2)), Box::new(Softmax)];
+        let mut rng = StdRng::from_seed(&[7]);
+        Network::with_activations(Geometry::new(vec![3, 4, 2]), activations, &mut rng)
+    }
+
+    fn assert_same(a: &Network, b: &Network) {
+        assert_eq!(a.geometry().layers(), b.geometry().layers());
+        for layer in 0..a.num_layers()-1 {
+            assert_eq!(a.weights(layer), b.weights(layer));
+            assert_eq!(a.biases(layer), b.biases(layer));
+            assert_eq!(a.activation(layer).name(), b.activation(layer).name());
+            assert_eq!(a.activation(layer).params(), b.activation(layer).params());
+        }
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let net = network();
+        let mut bytes = Vec::new();
+        net.write_binary(&mut bytes).unwrap();
+        assert_same(&net, &Network::read_binary(&mut &bytes[..]).unwrap());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let net = network();
+        assert_same(&net, &Network::from_json(&net.to_json().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn binary_errors() {
+        let mut bytes = Vec::new();
+        network().write_binary(&mut bytes).unwrap();
+
+        let mut corrupt = bytes.clone();
+        corrupt[30] ^= 1;
+        match Network::read_binary(&mut &corrupt[..]) {
+            Err(NetError::ChecksumMismatch { .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut future = bytes.clone();
+        future[5] = 99;
+        match Network::read_binary(&mut &future[..]) {
+            Err(NetError::UnsupportedVersion { found: 99, supported: VERSION }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    fn checkpoint() -> Checkpoint {
+        let mut net = network();
+        let mut adam = Adam::adamw(0.9, 0.99, 0.1);
+        let mut grads = Gradients::zeros(&net);
+        grads.weights[0].as_mut_slice()[1] = 0.5;
+        grads.biases[1].as_mut_slice()[0] = -0.25;
+        adam.step(&mut net, &grads, 0.01);
+        Checkpoint::new(net, Some(Box::new(adam)))
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let checkpoint = checkpoint();
+        let state = checkpoint.optimizer.as_ref().unwrap().state();
+
+        let mut bytes = Vec::new();
+        checkpoint.write_binary(&mut bytes).unwrap();
+        let binary = Checkpoint::read_binary(&mut &bytes[..]).unwrap();
+        assert_same(&checkpoint.network, &binary.network);
+        assert_eq!(binary.optimizer.unwrap().state(), state);
+
+        let json = Checkpoint::from_json(&checkpoint.to_json().unwrap()).unwrap();
+        assert_same(&checkpoint.network, &json.network);
+        assert_eq!(json.optimizer.unwrap().state(), state);
+
+        // A plain network load skips the optimizer state.
+        assert_same(&checkpoint.network, &Network::read_binary(&mut &bytes[..]).unwrap());
+    }
+
+    /// Wraps `payload` in a file header and checksum.
+    fn frame(version: u16, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.write_u16::<BigEndian>(version).unwrap();
+        bytes.write_u16::<BigEndian>(0).unwrap();
+        bytes.write_u64::<BigEndian>(payload.len() as u64).unwrap();
+        bytes.extend_from_slice(payload);
+        bytes.write_u32::<BigEndian>(crc32(payload)).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let net = network();
+        let mut payload = Vec::new();
+        net.write_payload(&mut payload).unwrap();
+        let bytes = frame(1, &payload);
+
+        let checkpoint = Checkpoint::read_binary(&mut &bytes[..]).unwrap();
+        assert_same(&net, &checkpoint.network);
+        assert!(checkpoint.optimizer.is_none());
+    }
+
+    #[test]
+    fn rejects_unread_payload() {
+        let mut payload = Vec::new();
+        network().write_payload(&mut payload).unwrap();
+        payload.push(0);
+        assert!(Checkpoint::read_binary(&mut &frame(VERSION, &payload)[..]).is_ok());
+
+        payload.push(0);
+        match Checkpoint::read_binary(&mut &frame(VERSION, &payload)[..]) {
+            Err(NetError::TrailingData(1)) => (),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        match Network::read_binary(&mut &frame(1, &payload)[..]) {
+            Err(NetError::TrailingData(2)) => (),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn json_shape_mismatch() {
+        let json = network().to_json().unwrap().replacen("[3, 4, 2]", "[3, 5, 2]", 1);
+        match Network::from_json(&json) {
+            Err(NetError::ShapeMismatch { layer: 0, expected: (5, 3), found: (4, 3) }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use rand;
+use rand::Rng;
+
+use math::{Matrix, Vector};
+use mnist::batch::{Batch, Batches, Examples, MiniBatcher};
+use super::cost::{Cost, Quadratic};
+use super::dropout::Dropout;
+use super::network::Network;
+use super::optimizer::{GradientDescent, Optimizer};
+use super::regularization::Regularization;
+use super::schedule::{EarlyStopping, Schedule, Scheduler};
+
+pub type TrainingPair = (Vec<f64>, Vec<f64>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradients {
+    pub weights: Vec<Matrix<f64>>,
+    pub biases: Vec<Vector<f64>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sgd {
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f64,
+    pub cost: Box<dyn Cost>,
+    pub regularization: Regularization,
+    pub dropout: Dropout,
+    pub optimizer: Box<dyn Optimizer>,
+    /// Adjusts `learning_rate` from epoch to epoch.
+    pub schedule: Schedule,
+    pub early_stopping: Option<EarlyStopping>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpochStats {
+    pub epoch: usize,
+    pub learning_rate: f64,
+    /// The mean cost on the training data, including the regularization 
+    /// penalty, as `total_cost` gives it.
+    pub training_cost: f64,
+    pub validation_cost: Option<f64>,
+    pub validation_accuracy: Option<f64>,
+}
+
+/// What happened during `Sgd::fit`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+    pub epochs: Vec<EpochStats>,
+    /// The epoch whose weights the network ended up with, when early 
+    /// stopping restored an earlier one.
+    pub best_epoch: Option<usize>,
+    pub stopped_early: bool,
+}
+
+impl Gradients {
+    pub fn zeros(net: &Network) -> Gradients {
+        let layers = net.num_layers()-1;
+        Gradients {
+            weights: (0..layers)
+                .map(|l| Matrix::zeros(net.weights(l).rows(), net.weights(l).cols()))
+                .collect(),
+            biases: (0..layers).map(|l| Vector::zeros(net.biases(l).len())).collect(),
+        }
+    }
+
+    pub fn add(&mut self, other: &Gradients) {
+        for (acc, g) in self.weights.iter_mut().zip(other.weights.iter()) {
+            acc.add_assign(g);
+        }
+        for (acc, g) in self.biases.iter_mut().zip(other.biases.iter()) {
+            acc.add_assign(g);
+        }
+    }
+
+    pub fn scale(&mut self, factor: f64) {
+        for w in &mut self.weights {
+            w.scale(factor);
+        }
+        for b in &mut self.biases {
+            b.scale(factor);
+        }
+    }
+
+    /// Whether these gradients have the same shape as `net`'s parameters.
+    pub fn fits(&self, net: &Network) -> bool {
+        self.weights.len() == net.num_layers()-1 
+            && self.weights.iter().enumerate().all(|(l, w)| {
+                w.rows() == net.weights(l).rows() && w.cols() == net.weights(l).cols()
+            })
+            && self.biases.iter().enumerate().all(|(l, b)| b.len() == net.biases(l).len())
+    }
+
+    /// The number of parameter blocks, two per weight layer.
+    pub fn num_blocks(&self) -> usize {
+        2 * self.weights.len()
+    }
+
+    /// Block `b` as a flat slice, in the order of `Network::parameters_mut`.
+    pub fn block(&self, b: usize) -> &[f64] {
+        if Gradients::is_weight_block(b) {
+            self.weights[b / 2].as_slice()
+        } else {
+            self.biases[b / 2].as_slice()
+        }
+    }
+
+    pub fn block_mut(&mut self, b: usize) -> &mut [f64] {
+        if Gradients::is_weight_block(b) {
+            self.weights[b / 2].as_mut_slice()
+        } else {
+            self.biases[b / 2].as_mut_slice()
+        }
+    }
+
+    pub fn is_weight_block(b: usize) -> bool {
+        b.is_multiple_of(2)
+    }
+}
+
+impl Sgd {
+    pub fn new(epochs: usize, batch_size: usize, learning_rate: f64) -> Sgd {
+        Sgd::with_cost(epochs, batch_size, learning_rate, Box::new(Quadratic))
+    }
+
+    pub fn with_cost(epochs: usize, batch_size: usize, learning_rate: f64, 
+                     cost: Box<dyn Cost>) -> Sgd 
+    {
+        assert!(batch_size > 0);
+        Sgd {
+            epochs,
+            batch_size,
+            learning_rate,
+            cost,
+            regularization: Regularization::None,
+            dropout: Dropout::none(),
+            optimizer: Box::new(GradientDescent),
+            schedule: Schedule::Constant,
+            early_stopping: None,
+        }
+    }
+
+    pub fn train<E: Examples + ?Sized>(&mut self, net: &mut Network, training_data: &E) {
+        self.train_with_rng(net, training_data, &mut rand::thread_rng());
+    }
+
+    pub fn train_with_rng<E, R>(&mut self, net: &mut Network, training_data: &E, rng: &mut R) 
+        where E: Examples + ?Sized, R: Rng
+    {
+        let no_validation: &[TrainingPair] = &[];
+        self.fit(net, training_data, no_validation, rng, |_, _| ());
+    }
+
+    /// Trains for up to `epochs` epochs, following `schedule`, and calls 
+    /// `on_epoch` after each one. Accuracy on `validation` drives the plateau 
+    /// schedule and early stopping; without validation data neither does 
+    /// anything.
+    /// When early stopping restores the best network, `optimizer` is rolled 
+    /// back to its state at the same epoch.
+    pub fn fit<E, V, R, F>(&mut self, net: &mut Network, training_data: &E, validation: &V, 
+                           rng: &mut R, mut on_epoch: F) -> History
+        where E: Examples + ?Sized, V: Examples + ?Sized, R: Rng, F: FnMut(&EpochStats, &Network)
+    {
+        let mut scheduler = Scheduler::new(self.schedule, self.learning_rate);
+        let mut history = History { epochs: Vec::new(), best_epoch: None, stopped_early: false };
+        // The optimizer's state goes with the weights it was built up for.
+        let mut best: Option<(f64, Network, Box<dyn Optimizer>)> = None;
+        let mut stale_epochs = 0;
+
+        for epoch in 0..self.epochs {
+            let learning_rate = scheduler.rate(epoch);
+            self.run_epoch(net, training_data, learning_rate, rng);
+
+            let n = training_data.len();
+            let training_cost = self.total_cost(net, training_data, n);
+            let (validation_cost, validation_accuracy) = if validation.is_empty() {
+                (None, None)
+            } else {
+                (Some(self.total_cost(net, validation, n)),
+                 Some(net.evaluate(validation) as f64 / validation.len() as f64))
+            };
+            let stats = EpochStats { 
+                epoch, learning_rate, training_cost, validation_cost, validation_accuracy,
+            };
+            on_epoch(&stats, net);
+            history.epochs.push(stats);
+
+            let accuracy = match validation_accuracy {
+                Some(accuracy) => accuracy,
+                None => continue,
+            };
+            scheduler.observe(accuracy);
+            if let Some(stopping) = self.early_stopping {
+                if best.as_ref().is_none_or(|b| accuracy > b.0 + stopping.min_delta) {
+                    best = Some((accuracy, net.clone(), self.optimizer.clone()));
+                    history.best_epoch = Some(epoch);
+                    stale_epochs = 0;
+                } else {
+                    stale_epochs += 1;
+                    if stale_epochs >= stopping.patience {
+                        history.stopped_early = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some((_, best_net, best_optimizer)) = best {
+            *net = best_net;
+            self.optimizer = best_optimizer;
+        }
+        history
+    }
+
+    /// Runs one pass of mini-batch updates over `training_data` in a random 
+    /// order. Dropout masks are drawn from `rng` too, so a seeded `rng` makes 
+    /// the whole epoch reproducible.
+    pub fn train_epoch<E, R>(&mut self, net: &mut Network, training_data: &E, rng: &mut R) 
+        where E: Examples + ?Sized, R: Rng
+    {
+        let learning_rate = self.learning_rate;
+        self.run_epoch(net, training_data, learning_rate, rng);
+    }
+
+    /// Takes one optimizer step on `batch`, drawn from a training set of 
+    /// `training_set_size` items.
+    pub fn update_mini_batch<R: Rng>(&mut self, net: &mut Network, batch: &Batch, 
+                                     training_set_size: usize, rng: &mut R) 
+    {
+        let learning_rate = self.learning_rate;
+        self.step(net, batch, training_set_size, learning_rate, rng);
+    }
+
+    fn run_epoch<E, R>(&mut self, net: &mut Network, training_data: &E, 
+                       learning_rate: f64, rng: &mut R) 
+        where E: Examples + ?Sized, R: Rng
+    {
+        let batches = MiniBatcher::new(self.batch_size, &mut *rng).epoch(training_data);
+        for batch in batches {
+            self.step(net, &batch, training_data.len(), learning_rate, rng);
+        }
+    }
+
+    fn step<R: Rng>(&mut self, net: &mut Network, batch: &Batch, 
+                    training_set_size: usize, learning_rate: f64, rng: &mut R) 
+    {
+        let mut nabla = Gradients::zeros(net);
+        for row in 0..batch.inputs.rows() {
+            let (x, y) = (batch.inputs.row(row), batch.targets.row(row));
+            if self.dropout.is_enabled() {
+                let masks = self.dropout.sample_masks(net, rng);
+                nabla.add(&backprop_with_masks(net, &*self.cost, x, y, &masks));
+            } else {
+                nabla.add(&backprop(net, &*self.cost, x, y));
+            }
+        }
+
+        nabla.scale(1.0 / batch.inputs.rows() as f64);
+        for layer in 0..net.num_layers()-1 {
+            self.regularization.add_gradient(net.weights(layer), &mut nabla.weights[layer], 
+                                             training_set_size);
+        }
+        self.optimizer.step(net, &nabla, learning_rate);
+    }
+
+    /// The mean cost of the network over `data`, plus the regularization 
+    /// penalty for a training set of `training_set_size` items.
+    pub fn total_cost<E: Examples + ?Sized>(&self, net: &Network, data: &E, 
+                                            training_set_size: usize) -> f64 
+    {
+        let total: f64 = Batches::in_order(data, self.batch_size)
+            .map(|batch| {
+                let outputs = net.feed_forward_batch(&batch.inputs);
+                (0..outputs.rows())
+                    .map(|row| self.cost.cost(&Vector::from_slice(outputs.row(row)), 
+                                              &Vector::from_slice(batch.targets.row(row))))
+                    .sum::<f64>()
+            })
+            .sum();
+        total / data.len() as f64 + self.regularization.penalty(net, training_set_size)
+    }
+}
+
+/// Computes the gradient of `cost` for a single training pair.
+pub fn backprop(net: &Network, cost: &dyn Cost, input: &[f64], target: &[f64]) -> Gradients {
+    backprop_with_masks(net, cost, input, target, &[])
+}
+
+/// Like `backprop`, but multiplies the output of each weight layer by its 
+/// dropout mask, if it has one.
+pub fn backprop_with_masks(net: &Network, cost: &dyn Cost, input: &[f64], target: &[f64], 
+                           masks: &[Option<Vector<f64>>]) -> Gradients 
+{
+    let num_weight_layers = net.num_layers()-1;
+    let mask = |layer: usize| masks.get(layer).and_then(|m| m.as_ref());
+
+    let mut activations = Vec::with_capacity(net.num_layers());
+    let mut zs = Vec::with_capacity(num_weight_layers);
+    activations.push(Vector::from_slice(input));
+    for layer in 0..num_weight_layers {
+        let z = net.weighted_input(layer, &activations[layer]);
+        let mut a = net.activation(layer).apply(&z);
+        if let Some(m) = mask(layer) {
+            a = a.hadamard(m);
+        }
+        activations.push(a);
+        zs.push(z);
+    }
+
+    let mut nabla = Gradients::zeros(net);
+    let output_layer = num_weight_layers-1;
+    let mut delta = cost.delta(&zs[output_layer], &activations[num_weight_layers], 
+                               &Vector::from_slice(target), net.activation(output_layer));
+
+    for layer in (0..num_weight_layers).rev() {
+        nabla.weights[layer] = Matrix::outer(&delta, &activations[layer]);
+        nabla.biases[layer] = delta.clone();
+        if layer > 0 {
+            let mut back = net.weights(layer).transpose_mul_vec(&delta);
+            if let Some(m) = mask(layer-1) {
+                back = back.hadamard(m);
+            }
+            delta = net.activation(layer-1).backprop(&zs[layer-1], &back);
+        }
+    }
+    nabla
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use net::cost::CrossEntropy;
+    use net::geom::Geometry;
+
+    #[test]
+    fn backprop_matches_numeric_gradient() {
+        let net = Network::new(Geometry::new(vec![3, 4, 2]));
+        let x = vec![0.2, -0.4, 0.9];
+        let y = vec![1.0, 0.0];
+        let nabla = backprop(&net, &CrossEntropy, &x, &y);
+
+        let cost = |net: &Network| -> f64 {
+            CrossEntropy.cost(&Vector::new(net.feed_forward(&x)), &Vector::from_slice(&y))
+        };
+
+        let eps = 1e-6;
+        for layer in 0..2 {
+            for i in 0..net.weights(layer).as_slice().len() {
+                let mut plus = net.clone();
+                plus.weights_mut(layer).as_mut_slice()[i] += eps;
+                let mut minus = net.clone();
+                minus.weights_mut(layer).as_mut_slice()[i] -= eps;
+                let numeric = (cost(&plus) - cost(&minus)) / (2.0 * eps);
+                assert!((numeric - nabla.weights[layer].as_slice()[i]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn masked_backprop_matches_numeric_gradient() {
+        let mut rng = StdRng::from_seed(&[5]);
+        let net = Network::with_rng(Geometry::new(vec![3, 6, 2]), &mut rng);
+        let masks = Dropout::uniform(1, 0.5).sample_masks(&net, &mut rng);
+        let x = vec![0.2, -0.4, 0.9];
+        let y = vec![1.0, 0.0];
+        let nabla = backprop_with_masks(&net, &CrossEntropy, &x, &y, &masks);
+
+        let cost = |net: &Network| -> f64 {
+            let mut a = Vector::from_slice(&x);
+            for (layer, mask) in masks.iter().enumerate() {
+                a = net.activation(layer).apply(&net.weighted_input(layer, &a));
+                if let Some(m) = mask {
+                    a = a.hadamard(m);
+                }
+            }
+            CrossEntropy.cost(&a, &Vector::from_slice(&y))
+        };
+
+        let eps = 1e-6;
+        for i in 0..net.weights(0).as_slice().len() {
+            let mut plus = net.clone();
+            plus.weights_mut(0).as_mut_slice()[i] += eps;
+            let mut minus = net.clone();
+            minus.weights_mut(0).as_mut_slice()[i] -= eps;
+            let numeric = (cost(&plus) - cost(&minus)) / (2.0 * eps);
+            assert!((numeric - nabla.weights[0].as_slice()[i]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn dropout_training_is_reproducible() {
+        let data: Vec<TrainingPair> = (0..20)
+            .map(|i| (vec![i as f64 / 20.0, 1.0 - i as f64 / 20.0], vec![(i % 2) as f64]))
+            .collect();
+        let mut sgd = Sgd::new(2, 4, 0.5);
+        sgd.dropout = Dropout::uniform(1, 0.5);
+
+        let run = || {
+            let mut rng = StdRng::from_seed(&[3]);
+            let mut net = Network::with_rng(Geometry::new(vec![2, 8, 1]), &mut rng);
+            sgd.clone().train_with_rng(&mut net, &data, &mut rng);
+            net
+        };
+        let (a, b) = (run(), run());
+        assert_eq!(a.weights(0), b.weights(0));
+        assert_eq!(a.weights(1), b.weights(1));
+    }
+
+    #[test]
+    fn early_stopping_restores_best_network() {
+        let data: Vec<TrainingPair> = (0..20)
+            .map(|i| (vec![i as f64 / 20.0], if i < 10 { vec![1.0, 0.0] } else { vec![0.0, 1.0] }))
+            .collect();
+        // A huge learning rate makes accuracy jump around, so the run stops 
+        // long before 200 epochs.
+        let mut sgd = Sgd::new(200, 5, 50.0);
+        sgd.early_stopping = Some(EarlyStopping::new(3));
+        sgd.schedule = Schedule::Exponential { gamma: 0.9 };
+
+        let mut rng = StdRng::from_seed(&[2]);
+        let mut net = Network::with_rng(Geometry::new(vec![1, 2]), &mut rng);
+        let mut snapshots = Vec::new();
+        let history = sgd.fit(&mut net, &data, &data, &mut rng, 
+                              |_, net| snapshots.push(net.clone()));
+
+        assert!(history.stopped_early);
+        assert_eq!(history.epochs.len(), snapshots.len());
+        let best = history.best_epoch.unwrap();
+        assert_eq!(history.epochs.len(), best + 4);
+        assert_eq!(net.weights(0), snapshots[best].weights(0));
+        assert!((history.epochs[1].learning_rate - 45.0).abs() < 1e-12);
+        for stats in &history.epochs {
+            assert!(stats.validation_accuracy.unwrap() 
+                    <= history.epochs[best].validation_accuracy.unwrap());
+        }
+    }
+
+    #[test]
+    fn early_stopping_restores_optimizer_state() {
+        use net::optimizer::Adam;
+
+        let data: Vec<TrainingPair> = (0..20)
+            .map(|i| (vec![i as f64 / 20.0], if i < 10 { vec![1.0, 0.0] } else { vec![0.0, 1.0] }))
+            .collect();
+        // No epoch can improve on the first by a whole unit of accuracy.
+        let mut sgd = Sgd::new(10, 5, 0.1);
+        sgd.optimizer = Box::new(Adam::new(0.9, 0.999));
+        sgd.early_stopping = Some(EarlyStopping { patience: 1, min_delta: 1.0 });
+
+        let mut rng = StdRng::from_seed(&[2]);
+        let mut net = Network::with_rng(Geometry::new(vec![1, 2]), &mut rng);
+        let history = sgd.fit(&mut net, &data, &data, &mut rng, |_, _| ());
+
+        assert_eq!(history.epochs.len(), 2);
+        assert_eq!(history.best_epoch, Some(0));
+        // Four mini-batches in the first epoch only.
+        assert_eq!(sgd.optimizer.state().steps, 4);
+    }
+
+    #[test]
+    fn reported_costs_include_penalty() {
+        let data: Vec<TrainingPair> = (0..20)
+            .map(|i| (vec![i as f64 / 20.0], if i < 10 { vec![1.0, 0.0] } else { vec![0.0, 1.0] }))
+            .collect();
+        let mut sgd = Sgd::new(1, 5, 0.1);
+        sgd.regularization = Regularization::L2(5.0);
+
+        let mut rng = StdRng::from_seed(&[2]);
+        let mut net = Network::with_rng(Geometry::new(vec![1, 2]), &mut rng);
+        let history = sgd.fit(&mut net, &data, &data[..10], &mut rng, |_, _| ());
+
+        let stats = &history.epochs[0];
+        let penalty = sgd.regularization.penalty(&net, 20);
+        assert!(penalty > 0.0);
+        assert_eq!(stats.training_cost, sgd.total_cost(&net, &data, 20));
+        assert_eq!(stats.validation_cost, Some(sgd.total_cost(&net, &data[..10], 20)));
+        let unregularized = Sgd::new(1, 5, 0.1).total_cost(&net, &data, 20);
+        assert!((stats.training_cost - unregularized - penalty).abs() < 1e-12);
+    }
+}